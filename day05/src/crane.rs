@@ -0,0 +1,198 @@
+use std::collections::VecDeque;
+use std::fmt::{Display, Formatter};
+
+use crate::Instr;
+
+/// A crane model that decides how a group of crates lands on the destination stack
+pub trait Crane {
+    /// Takes the `held` crates (bottom first, exactly as they sat on the source stack) and
+    /// rearranges them into the order they end up in on the destination stack.
+    ///
+    /// Returns the number of lifts the crane needed to do it
+    fn lift(&mut self, instr: &Instr, held: &mut VecDeque<char>) -> usize;
+}
+
+impl<C: Crane + ?Sized> Crane for &mut C {
+    fn lift(&mut self, instr: &Instr, held: &mut VecDeque<char>) -> usize {
+        (**self).lift(instr, held)
+    }
+}
+
+impl<C: Crane + ?Sized> Crane for Box<C> {
+    fn lift(&mut self, instr: &Instr, held: &mut VecDeque<char>) -> usize {
+        (**self).lift(instr, held)
+    }
+}
+
+/// Moves crates one at a time (part 1)
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn lift(&mut self, _: &Instr, held: &mut VecDeque<char>) -> usize {
+        held.make_contiguous().reverse();
+        held.len()
+    }
+}
+
+/// Moves every crate at once (part 2)
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn lift(&mut self, _: &Instr, held: &mut VecDeque<char>) -> usize {
+        usize::from(!held.is_empty())
+    }
+}
+
+/// Moves at most `max` crates per lift, keeping the order within each lift
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct MaxLift(pub usize);
+
+impl Crane for MaxLift {
+    fn lift(&mut self, _: &Instr, held: &mut VecDeque<char>) -> usize {
+        let max = self.0.max(1);
+
+        // The top chunk is lifted first so it ends up at the bottom of the destination
+        let chunks: Vec<Vec<char>> = held
+            .make_contiguous()
+            .rchunks(max)
+            .map(|c| c.to_vec())
+            .collect();
+
+        held.clear();
+        for chunk in chunks.iter() {
+            held.extend(chunk);
+        }
+
+        chunks.len()
+    }
+}
+
+/// Moves every crate at once but swaps each pair, counting from the top, as it sets them down.
+/// An odd crate left at the bottom stays where it is
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct PairSwap;
+
+impl Crane for PairSwap {
+    fn lift(&mut self, _: &Instr, held: &mut VecDeque<char>) -> usize {
+        for pair in held.make_contiguous().rchunks_exact_mut(2) {
+            pair.swap(0, 1);
+        }
+
+        usize::from(!held.is_empty())
+    }
+}
+
+/// How much energy each part of a move costs
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct CostModel {
+    pub per_lift: u64,
+    pub per_crate: u64,
+    pub per_stack_travelled: u64,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            per_lift: 1,
+            per_crate: 0,
+            per_stack_travelled: 0,
+        }
+    }
+}
+
+/// Running totals collected by a [`Metered`] crane
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Cost {
+    pub instrs: usize,
+    pub lifts: usize,
+    pub crates: usize,
+    pub energy: u64,
+}
+
+impl Display for Cost {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} instrs, {} lifts, {} crates, {} energy",
+            self.instrs, self.lifts, self.crates, self.energy
+        )
+    }
+}
+
+/// Wraps another crane and keeps track of what its moves cost
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Metered<C> {
+    pub crane: C,
+    pub model: CostModel,
+    pub cost: Cost,
+}
+
+impl<C: Crane> Metered<C> {
+    pub fn new(crane: C, model: CostModel) -> Self {
+        Metered {
+            crane,
+            model,
+            cost: Cost::default(),
+        }
+    }
+}
+
+impl<C: Crane> Crane for Metered<C> {
+    fn lift(&mut self, instr: &Instr, held: &mut VecDeque<char>) -> usize {
+        let lifts = self.crane.lift(instr, held);
+        let travelled = instr.src.abs_diff(instr.dst) as u64;
+
+        self.cost.instrs += 1;
+        self.cost.lifts += lifts;
+        self.cost.crates += held.len();
+        self.cost.energy += lifts as u64
+            * (self.model.per_lift + travelled * self.model.per_stack_travelled)
+            + held.len() as u64 * self.model.per_crate;
+
+        lifts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::get_ans;
+
+    #[test]
+    fn test_other_cranes() {
+        assert_eq!("CMZ", get_ans(&mut MaxLift(1)));
+        assert_eq!("MCD", get_ans(&mut MaxLift(3)));
+        assert_eq!("MCZ", get_ans(&mut MaxLift(2)));
+        assert_eq!("CMN", get_ans(&mut PairSwap));
+    }
+
+    #[test]
+    fn test_metered_fleet() {
+        let model = CostModel {
+            per_lift: 10,
+            per_crate: 1,
+            per_stack_travelled: 2,
+        };
+
+        let mut fleet: Vec<Metered<Box<dyn Crane>>> = vec![
+            Metered::new(Box::new(CrateMover9000), model),
+            Metered::new(Box::new(CrateMover9001), model),
+            Metered::new(Box::new(MaxLift(2)), model),
+        ];
+
+        for crane in fleet.iter_mut() {
+            get_ans(crane);
+        }
+
+        let costs: Vec<_> = fleet
+            .iter()
+            .map(|m| (m.cost.lifts, m.cost.energy))
+            .collect();
+        assert_eq!(vec![(7, 97), (4, 57), (5, 71)], costs);
+        assert!(fleet
+            .iter()
+            .all(|m| m.cost.instrs == 4 && m.cost.crates == 7));
+    }
+}
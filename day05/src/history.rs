@@ -0,0 +1,285 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::str::Lines;
+
+use crate::crane::Crane;
+use crate::{Boxes, Instr, RunError};
+
+/// A single instruction that was carried out, with enough detail to undo or redo it
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Step {
+    pub instr: Instr,
+    pub line_no: usize,
+    pub taken: VecDeque<char>,
+    pub placed: VecDeque<char>,
+}
+
+/// Runs validated instructions against a set of stacks while recording every move so the
+/// stacks can be stepped backward and forward through them
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct History {
+    boxes: Boxes,
+    steps: Vec<Step>,
+    pos: usize,
+}
+
+impl History {
+    pub fn new(boxes: Boxes) -> Self {
+        History {
+            boxes,
+            steps: Vec::with_capacity(512),
+            pos: 0,
+        }
+    }
+
+    /// Parses the drawing and runs every instruction after it, stopping at the first one
+    /// that is invalid
+    pub fn parse_and_run<C: Crane + ?Sized>(
+        problem: &str,
+        crane: &mut C,
+    ) -> Result<Self, RunError> {
+        let mut lines = problem.lines();
+        let mut boxes = Boxes::default();
+        let used = boxes.add_lines(&mut lines);
+
+        let mut history = History::new(boxes);
+        history.run_all(&mut lines, used + 1, crane)?;
+        Ok(history)
+    }
+
+    /// Runs every instruction in `lines`, the first of which is line `first_line_no`.  Blank
+    /// lines are skipped; anything else that isn't a valid instruction is an error
+    pub fn run_all<C: Crane + ?Sized>(
+        &mut self,
+        lines: &mut Lines,
+        first_line_no: usize,
+        crane: &mut C,
+    ) -> Result<(), RunError> {
+        for (line_no, line) in (first_line_no..).zip(lines) {
+            let Some(instr) = Instr::parse_line(line_no, line)? else {
+                continue;
+            };
+
+            self.run(instr, line_no, line, crane)?;
+        }
+
+        Ok(())
+    }
+
+    /// Validates and runs a single instruction.  Any steps that were undone are dropped
+    pub fn run<C: Crane + ?Sized>(
+        &mut self,
+        instr: Instr,
+        line_no: usize,
+        line: &str,
+        crane: &mut C,
+    ) -> Result<(), RunError> {
+        self.boxes
+            .check(&instr)
+            .map_err(|kind| RunError::new(line_no, line, kind))?;
+
+        let (taken, placed) = self.boxes.transfer(&instr, crane);
+
+        self.steps.truncate(self.pos);
+        self.steps.push(Step {
+            instr,
+            line_no,
+            taken,
+            placed,
+        });
+        self.pos += 1;
+
+        Ok(())
+    }
+
+    /// The stacks as of the current step
+    pub fn boxes(&self) -> &Boxes {
+        &self.boxes
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// How many of the recorded steps have been applied
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Undoes the last applied step, returning false if we're already at the start
+    pub fn back(&mut self) -> bool {
+        if self.pos == 0 {
+            return false;
+        }
+
+        self.pos -= 1;
+        let step = &self.steps[self.pos];
+
+        let dst = &mut self.boxes.stacks[step.instr.dst];
+        dst.truncate(dst.len() - step.placed.len());
+        self.boxes.stacks[step.instr.src].extend(step.taken.iter());
+
+        true
+    }
+
+    /// Re-applies the next recorded step, returning false if there isn't one
+    pub fn forward(&mut self) -> bool {
+        if self.pos == self.steps.len() {
+            return false;
+        }
+
+        let step = &self.steps[self.pos];
+        self.pos += 1;
+
+        let src = &mut self.boxes.stacks[step.instr.src];
+        src.truncate(src.len() - step.taken.len());
+        self.boxes.stacks[step.instr.dst].extend(step.placed.iter());
+
+        true
+    }
+
+    /// Replays (or rewinds) to the state right after `step` instructions have run.  Steps past
+    /// the end of the history stop at the last one
+    pub fn seek(&mut self, step: usize) {
+        let step = step.min(self.steps.len());
+
+        while self.pos > step {
+            self.back();
+        }
+        while self.pos < step {
+            self.forward();
+        }
+    }
+
+    /// Rewinds to the start and writes out the stacks after every step, ending up back at the
+    /// last step
+    pub fn animate<W: Write>(&mut self, out: &mut W) -> std::fmt::Result {
+        self.seek(0);
+        writeln!(out, "{}", self.boxes)?;
+
+        while self.forward() {
+            let step = &self.steps[self.pos - 1];
+            writeln!(
+                out,
+                "\n{} (line {})\n{}",
+                step.instr, step.line_no, self.boxes
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crane::CrateMover9000;
+    use crate::tests::{make_problem, make_setup};
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_validated_errors() {
+        let problem = format!("{}\nmove 5 from 3 to 1", make_problem());
+        let err = History::parse_and_run(&problem, &mut CrateMover9000).unwrap_err();
+        assert_eq!(10, err.line_no);
+        assert_eq!(ErrorKind::NotEnoughCrates { have: 4 }, err.kind);
+        assert_eq!(
+            "line 10 `move 5 from 3 to 1`: the source stack only has 4 crates",
+            err.to_string()
+        );
+
+        let problem = format!("{}\nmove 1 from 1 to 10", make_problem());
+        let err = History::parse_and_run(&problem, &mut CrateMover9000).unwrap_err();
+        assert_eq!((10, ErrorKind::NoSuchStack(10)), (err.line_no, err.kind));
+
+        let problem = format!("{}\n\nmove one from 1 to 2", make_problem());
+        let err = History::parse_and_run(&problem, &mut CrateMover9000).unwrap_err();
+        assert_eq!((11, ErrorKind::Unparseable), (err.line_no, err.kind));
+    }
+
+    #[test]
+    fn test_history() {
+        let mut history = History::parse_and_run(&make_problem(), &mut CrateMover9000).unwrap();
+        assert_eq!(4, history.steps().len());
+        assert_eq!("CMZ", history.boxes().answer());
+
+        let mut start = Boxes::default();
+        start.add_lines(&mut make_setup().lines());
+
+        let mut answers = vec![];
+        while history.back() {
+            answers.push(history.boxes().answer());
+        }
+        assert_eq!(vec!["M Z", "CZ", "DCP", "NDP"], answers);
+        assert_eq!(&start, history.boxes());
+
+        assert!(history.forward());
+        assert_eq!("DCP", history.boxes().answer());
+
+        history.seek(99);
+        assert_eq!(
+            (4, "CMZ".to_string()),
+            (history.pos(), history.boxes().answer())
+        );
+        history.seek(0);
+        assert_eq!(&start, history.boxes());
+
+        // Running a new instruction drops the steps that were undone
+        history.seek(2);
+        history
+            .run(
+                Instr::new(2, 0, 1),
+                99,
+                "move 1 from 3 to 1",
+                &mut CrateMover9000,
+            )
+            .unwrap();
+        assert_eq!((3, 3), (history.pos(), history.steps().len()));
+        assert!(!history.forward());
+    }
+
+    #[rustfmt::skip]
+    #[test]
+    fn test_animate() {
+        let mut history = History::parse_and_run(&make_problem(), &mut CrateMover9000).unwrap();
+        let mut frames = String::new();
+        history.animate(&mut frames).unwrap();
+
+        let want = [
+            "    [D]    ",
+            "[N] [C]    ",
+            "[Z] [M] [P]",
+            " 1   2   3 ",
+            "",
+            "move 1 from 2 to 1 (line 6)",
+            "[D]        ",
+            "[N] [C]    ",
+            "[Z] [M] [P]",
+            " 1   2   3 ",
+            "",
+            "move 3 from 1 to 3 (line 7)",
+            "        [Z]",
+            "        [N]",
+            "    [C] [D]",
+            "    [M] [P]",
+            " 1   2   3 ",
+            "",
+            "move 2 from 2 to 1 (line 8)",
+            "        [Z]",
+            "        [N]",
+            "[M]     [D]",
+            "[C]     [P]",
+            " 1   2   3 ",
+            "",
+            "move 1 from 1 to 2 (line 9)",
+            "        [Z]",
+            "        [N]",
+            "        [D]",
+            "[C] [M] [P]",
+            " 1   2   3 ",
+            "",
+        ];
+        assert_eq!(want.join("\n"), frames);
+        assert_eq!((4, "CMZ".to_string()), (history.pos(), history.boxes().answer()));
+    }
+}
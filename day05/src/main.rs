@@ -1,35 +1,114 @@
 extern crate core;
 
+mod crane;
+mod history;
+mod planner;
+
+use crate::crane::{CostModel, Crane, CrateMover9000, CrateMover9001, MaxLift, Metered, PairSwap};
+use crate::history::History;
+use crate::planner::Goal;
 use std::collections::VecDeque;
+use std::fmt::{Display, Formatter, Write};
 use std::io::{Cursor, Read, Seek, SeekFrom};
 use std::str::Lines;
 
 const PART_1: bool = false;
+const COMPARE_FLEET: bool = false;
+/// Print the stacks after every instruction
+const ANIMATE: bool = false;
+/// Rewind to just after this many instructions before printing the answer
+const REPLAY_TO: Option<usize> = None;
+/// Plan the moves from the starting stacks to this drawing or top-of-stack string
+const PLAN_TO: Option<&str> = None;
+/// The full puzzle input has hundreds of moves from every state, so plans have to stay short
+const PLAN_MAX_MOVES: usize = 2;
+const PLAN_MAX_STATES: usize = 1_000_000;
 
 static PART_1_DATA: &str = include_str!("input");
 
-fn main() {
+fn main() -> Result<(), RunError> {
+    if COMPARE_FLEET {
+        compare_fleet()?;
+    }
+
+    if let Some(goal) = PLAN_TO {
+        if PART_1 {
+            print_plan(goal, &CrateMover9000);
+        } else {
+            print_plan(goal, &CrateMover9001);
+        }
+    }
+
     if PART_1 {
-        part1();
+        part1()
     } else {
-        part2();
+        part2()
     }
 }
 
-fn part1() {
-    let mut boxes = Boxes::default();
-    boxes.parse_and_run(PART_1_DATA, false);
-    println!("{}", boxes.answer());
+fn part1() -> Result<(), RunError> {
+    print_answer(History::parse_and_run(PART_1_DATA, &mut CrateMover9000)?);
+    Ok(())
 }
 
-fn part2() {
-    let mut boxes = Boxes::default();
-    boxes.parse_and_run(PART_1_DATA, true);
-    println!("{}", boxes.answer());
+fn part2() -> Result<(), RunError> {
+    print_answer(History::parse_and_run(PART_1_DATA, &mut CrateMover9001)?);
+    Ok(())
 }
 
-#[derive(Eq, PartialEq, Clone, Debug, Default)]
-pub struct Boxes([VecDeque<char>; 9]);
+fn print_answer(mut history: History) {
+    if ANIMATE {
+        let mut frames = String::with_capacity(1 << 16);
+        history.animate(&mut frames).unwrap();
+        println!("{frames}");
+    }
+
+    if let Some(step) = REPLAY_TO {
+        history.seek(step);
+        if let Some(last) = history.steps()[..history.pos()].last() {
+            println!("After line {}: {}", last.line_no, last.instr);
+        }
+    }
+
+    println!("{}", history.boxes().answer());
+}
+
+fn print_plan<C: Crane + Clone>(goal: &str, crane: &C) {
+    let mut start = Boxes::default();
+    start.add_lines(&mut PART_1_DATA.lines());
+
+    let goal = Goal::parse(goal);
+    match planner::plan(&start, &goal, crane, PLAN_MAX_MOVES, PLAN_MAX_STATES) {
+        Ok(plan) => println!("{plan}"),
+        Err(e) => println!("No plan found: {e}"),
+    }
+}
+
+fn compare_fleet() -> Result<(), RunError> {
+    let fleet: Vec<(&str, Box<dyn Crane>)> = vec![
+        ("CrateMover 9000", Box::new(CrateMover9000)),
+        ("CrateMover 9001", Box::new(CrateMover9001)),
+        ("Max lift 2", Box::new(MaxLift(2))),
+        ("Max lift 5", Box::new(MaxLift(5))),
+        ("Pair swap", Box::new(PairSwap)),
+    ];
+
+    for (name, crane) in fleet {
+        let mut crane = Metered::new(crane, CostModel::default());
+        let mut boxes = Boxes::default();
+        boxes.parse_and_run(PART_1_DATA, &mut crane)?;
+        println!("{name:>15}: {} | {}", boxes.answer(), crane.cost);
+    }
+
+    Ok(())
+}
+
+#[derive(Eq, PartialEq, Hash, Clone, Debug, Default)]
+pub struct Boxes {
+    stacks: [VecDeque<char>; 9],
+    /// How many columns the drawing had, so it can be drawn back out the same way
+    cols: usize,
+}
 
 impl Boxes {
     pub fn add(&mut self, line: &str) -> bool {
@@ -42,9 +121,11 @@ impl Boxes {
 
         let mut col = 0;
         while rdr.read_exact(&mut group).is_ok() {
+            self.cols = self.cols.max(col + 1);
+
             if group[0] == b'[' {
                 let label = group[1] as char;
-                self.0[col].push_front(label);
+                self.stacks[col].push_front(label);
             }
 
             // Skip the next space.  If we fail then we're at the end so exit
@@ -58,44 +139,110 @@ impl Boxes {
         true
     }
 
-    pub fn add_lines(&mut self, lines: &mut Lines) {
+    /// Reads the drawing up to and including the blank line after it, returning how many
+    /// lines were used
+    pub fn add_lines(&mut self, lines: &mut Lines) -> usize {
+        let mut used = 0;
         for line in lines {
+            used += 1;
             if !self.add(line) {
-                return;
+                break;
             }
         }
+        used
     }
 
-    pub fn run(&mut self, instr: Instr, at_once: bool) {
-        if at_once {
-            let src = &mut self.0[instr.src];
-            let idx = src.len() - instr.count;
-            let tail = src.split_off(idx);
-            self.0[instr.dst].extend(tail);
-        } else {
-            for _ in 0..instr.count {
-                let item = self.0[instr.src].pop_back().unwrap();
-                self.0[instr.dst].push_back(item);
+    /// Validates and runs a single instruction
+    pub fn run<C: Crane + ?Sized>(&mut self, instr: Instr, crane: &mut C) -> Result<(), ErrorKind> {
+        self.check(&instr)?;
+        self.transfer(&instr, crane);
+        Ok(())
+    }
+
+    /// Checks that `instr` only refers to existing stacks and doesn't move more crates than
+    /// the source stack holds
+    pub fn check(&self, instr: &Instr) -> Result<(), ErrorKind> {
+        for stack in [instr.src, instr.dst] {
+            if stack >= self.stacks.len() {
+                return Err(ErrorKind::NoSuchStack(stack + 1));
             }
         }
+
+        let have = self.stacks[instr.src].len();
+        if have < instr.count {
+            return Err(ErrorKind::NotEnoughCrates { have });
+        }
+
+        Ok(())
     }
 
-    pub fn run_all(&mut self, lines: &mut Lines, at_once: bool) {
-        for line in lines {
-            if let Ok(instr) = line.try_into() {
-                self.run(instr, at_once)
-            }
+    /// Carries out `instr`, returning the crates in the order they were taken off the source
+    /// and the order they landed on the destination.  `instr` has to have passed
+    /// [`Boxes::check`] first
+    fn transfer<C: Crane + ?Sized>(
+        &mut self,
+        instr: &Instr,
+        crane: &mut C,
+    ) -> (VecDeque<char>, VecDeque<char>) {
+        let src = &mut self.stacks[instr.src];
+        let idx = src.len() - instr.count;
+        let taken = src.split_off(idx);
+        let mut placed = taken.clone();
+
+        crane.lift(instr, &mut placed);
+        if instr.src == instr.dst {
+            // Setting the crates back down where they came from leaves the stack as it was
+            placed = taken.clone();
         }
+
+        self.stacks[instr.dst].extend(placed.iter());
+        (taken, placed)
     }
 
-    pub fn parse_and_run(&mut self, problem: &str, at_once: bool) {
+    /// Runs every instruction in `lines`, the first of which is line `first_line_no`, stopping
+    /// at the first one that is invalid.  Blank lines are skipped
+    pub fn run_all<C: Crane + ?Sized>(
+        &mut self,
+        lines: &mut Lines,
+        first_line_no: usize,
+        crane: &mut C,
+    ) -> Result<(), RunError> {
+        for (line_no, line) in (first_line_no..).zip(lines) {
+            let Some(instr) = Instr::parse_line(line_no, line)? else {
+                continue;
+            };
+
+            self.run(instr, crane)
+                .map_err(|kind| RunError::new(line_no, line, kind))?;
+        }
+
+        Ok(())
+    }
+
+    /// Parses the drawing and runs every instruction after it
+    pub fn parse_and_run<C: Crane + ?Sized>(
+        &mut self,
+        problem: &str,
+        crane: &mut C,
+    ) -> Result<(), RunError> {
         let mut lines = problem.lines();
-        self.add_lines(&mut lines);
-        self.run_all(&mut lines, at_once);
+        let used = self.add_lines(&mut lines);
+        self.run_all(&mut lines, used + 1, crane)
+    }
+
+    /// How many stacks are in use, counting any empty ones the drawing had
+    pub fn cols(&self) -> usize {
+        let used = self
+            .stacks
+            .iter()
+            .rposition(|s| !s.is_empty())
+            .map_or(0, |idx| idx + 1);
+
+        self.cols.max(used)
     }
 
     pub fn answer(&self) -> String {
-        self.0
+        self.stacks
             .iter()
             .map(|b| b.back().unwrap_or(&' '))
             .collect::<String>()
@@ -104,6 +251,36 @@ impl Boxes {
     }
 }
 
+impl Display for Boxes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let cols = self.cols();
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+
+        for row in (0..height).rev() {
+            for (col, stack) in self.stacks[..cols].iter().enumerate() {
+                if col != 0 {
+                    f.write_char(' ')?;
+                }
+
+                match stack.get(row) {
+                    Some(label) => write!(f, "[{label}]")?,
+                    None => f.write_str("   ")?,
+                }
+            }
+            writeln!(f)?;
+        }
+
+        for col in 0..cols {
+            if col != 0 {
+                f.write_char(' ')?;
+            }
+            write!(f, " {} ", col + 1)?;
+        }
+
+        Ok(())
+    }
+}
+
 #[derive(Eq, PartialEq, Clone, Debug, Default)]
 pub struct Instr {
     pub src: usize,
@@ -127,21 +304,46 @@ impl Instr {
         if parts.next().unwrap_or_default() != "from" {
             return None;
         }
-        let mut src = parts.next()?.parse().ok()?;
+        let src: usize = parts.next()?.parse().ok()?;
 
         if parts.next().unwrap_or_default() != "to" {
             return None;
         }
-        let mut dst = parts.next()?.parse().ok()?;
+        let dst: usize = parts.next()?.parse().ok()?;
 
         if parts.next().is_some() {
             return None;
         }
 
-        src -= 1;
-        dst -= 1;
+        // Stacks are numbered from 1 so there's no stack 0
+        Some(Instr {
+            count,
+            src: src.checked_sub(1)?,
+            dst: dst.checked_sub(1)?,
+        })
+    }
+
+    /// Parses line `line_no` of the instructions, which is either blank or has to be valid
+    pub fn parse_line(line_no: usize, line: &str) -> Result<Option<Self>, RunError> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+
+        Instr::parse(line)
+            .map(Some)
+            .ok_or_else(|| RunError::new(line_no, line, ErrorKind::Unparseable))
+    }
+}
 
-        Some(Instr { count, src, dst })
+impl Display for Instr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.count,
+            self.src + 1,
+            self.dst + 1
+        )
     }
 }
 
@@ -153,13 +355,58 @@ impl TryFrom<&str> for Instr {
     }
 }
 
+/// Why an instruction couldn't be run
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum ErrorKind {
+    Unparseable,
+    NoSuchStack(usize),
+    NotEnoughCrates { have: usize },
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Unparseable => write!(f, "not a valid instruction"),
+            ErrorKind::NoSuchStack(n) => write!(f, "there is no stack {n}"),
+            ErrorKind::NotEnoughCrates { have } => {
+                write!(f, "the source stack only has {have} crates")
+            }
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct RunError {
+    pub line_no: usize,
+    pub line: String,
+    pub kind: ErrorKind,
+}
+
+impl RunError {
+    pub fn new(line_no: usize, line: &str, kind: ErrorKind) -> Self {
+        RunError {
+            line_no,
+            line: line.to_string(),
+            kind,
+        }
+    }
+}
+
+impl Display for RunError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} `{}`: {}", self.line_no, self.line, self.kind)
+    }
+}
+
+impl std::error::Error for RunError {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[rustfmt::skip]
-    fn make_setup() -> String {
-        let parts = vec![
+    pub(crate) fn make_setup() -> String {
+        let parts = [
             "    [D]    ",
             "[N] [C]    ",
             "[Z] [M] [P]",
@@ -169,7 +416,7 @@ mod tests {
         parts.join("\n")
     }
 
-    fn make_instr() -> String {
+    pub(crate) fn make_instr() -> String {
         "
 move 1 from 2 to 1
 move 3 from 1 to 3
@@ -180,7 +427,7 @@ move 1 from 1 to 2
         .to_string()
     }
 
-    fn make_problem() -> String {
+    pub(crate) fn make_problem() -> String {
         format!("{}\n\n{}", make_setup(), make_instr())
     }
 
@@ -189,7 +436,7 @@ move 1 from 1 to 2
         let mut boxes = Boxes::default();
         boxes.add_lines(&mut make_problem().lines());
 
-        for (idx, col) in boxes.0.iter().enumerate() {
+        for (idx, col) in boxes.stacks.iter().enumerate() {
             match idx {
                 0 => assert_eq!(VecDeque::from(['Z', 'N']), *col),
                 1 => assert_eq!(VecDeque::from(['M', 'C', 'D']), *col),
@@ -213,17 +460,76 @@ move 1 from 1 to 2
 
     #[test]
     fn test_one_at_a_time() {
-        assert_eq!("CMZ", get_ans(false))
+        assert_eq!("CMZ", get_ans(&mut CrateMover9000))
     }
 
     #[test]
     fn test_all_at_once() {
-        assert_eq!("MCD", get_ans(true))
+        assert_eq!("MCD", get_ans(&mut CrateMover9001))
+    }
+
+    #[test]
+    fn test_same_stack() {
+        let mut boxes = Boxes::default();
+        boxes.add_lines(&mut make_setup().lines());
+        boxes.run(Instr::new(1, 1, 3), &mut CrateMover9000).unwrap();
+        assert_eq!(VecDeque::from(['M', 'C', 'D']), boxes.stacks[1]);
+    }
+
+    #[test]
+    fn test_parse_bad_stack() {
+        assert_eq!(None, Instr::parse("move 1 from 0 to 2"));
+    }
+
+    #[test]
+    fn test_boxes_errors() {
+        let problem = format!("{}\nmove 5 from 3 to 1", make_problem());
+        let mut boxes = Boxes::default();
+        let err = boxes
+            .parse_and_run(&problem, &mut CrateMover9000)
+            .unwrap_err();
+        assert_eq!(
+            (10, ErrorKind::NotEnoughCrates { have: 4 }),
+            (err.line_no, err.kind)
+        );
+        assert_eq!("CMZ", boxes.answer());
+
+        let mut boxes = Boxes::default();
+        boxes.add_lines(&mut make_setup().lines());
+        assert_eq!(
+            Err(ErrorKind::NoSuchStack(10)),
+            boxes.run(Instr::new(9, 0, 1), &mut CrateMover9000)
+        );
+        assert_eq!(
+            Err(ErrorKind::NotEnoughCrates { have: 1 }),
+            boxes.run(Instr::new(2, 0, 2), &mut CrateMover9000)
+        );
+
+        let err = boxes
+            .run_all(
+                &mut "\nmove 1 from 1 to 2\nmove x".lines(),
+                5,
+                &mut CrateMover9000,
+            )
+            .unwrap_err();
+        assert_eq!((7, ErrorKind::Unparseable), (err.line_no, err.kind));
+        assert_eq!(VecDeque::from(['M', 'C', 'D', 'N']), boxes.stacks[1]);
+    }
+
+    #[test]
+    fn test_render_round_trip() {
+        let mut boxes = Boxes::default();
+        boxes.add_lines(&mut make_setup().lines());
+        assert_eq!(make_setup(), boxes.to_string());
+
+        let mut reparsed = Boxes::default();
+        reparsed.add_lines(&mut boxes.to_string().lines());
+        assert_eq!(boxes, reparsed);
     }
 
-    fn get_ans(at_once: bool) -> String {
+    pub(crate) fn get_ans<C: Crane + ?Sized>(crane: &mut C) -> String {
         let mut boxes = Boxes::default();
-        boxes.parse_and_run(&make_problem(), at_once);
+        boxes.parse_and_run(&make_problem(), crane).unwrap();
         boxes.answer()
     }
 }
@@ -0,0 +1,293 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+use crate::crane::Crane;
+use crate::{Boxes, Instr};
+
+/// What the stacks should look like once the plan has run
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Goal {
+    /// Every stack has to match exactly
+    Stacks(Box<Boxes>),
+    /// Only the crates on top have to match, as read by [`Boxes::answer`]
+    Tops(String),
+}
+
+impl Goal {
+    /// Reads either a full drawing (anything with a crate in it) or a top-of-stack string
+    pub fn parse(goal: &str) -> Self {
+        if goal.contains('[') {
+            let mut boxes = Boxes::default();
+            boxes.add_lines(&mut goal.lines());
+            Goal::Stacks(Box::new(boxes))
+        } else {
+            Goal::Tops(goal.trim().to_string())
+        }
+    }
+
+    pub fn reached(&self, boxes: &Boxes) -> bool {
+        match self {
+            Goal::Stacks(want) => want.stacks == boxes.stacks,
+            Goal::Tops(want) => *want == boxes.answer(),
+        }
+    }
+
+    /// A quick check that the goal isn't out of reach whatever the moves: no crane adds or
+    /// removes crates, and none can use stacks past the last one in `start`
+    fn possible_from(&self, start: &Boxes) -> bool {
+        let have = crate_counts(start.stacks.iter().flatten().copied());
+
+        match self {
+            Goal::Stacks(want) => {
+                want.cols() <= start.cols()
+                    && crate_counts(want.stacks.iter().flatten().copied()) == have
+            }
+            Goal::Tops(want) => {
+                want.chars().count() <= start.cols()
+                    && crate_counts(want.chars())
+                        .iter()
+                        .all(|(c, n)| have.get(c).is_some_and(|have| have >= n))
+            }
+        }
+    }
+}
+
+/// How many of each crate there are, ignoring the gaps left by empty stacks
+fn crate_counts(crates: impl Iterator<Item = char>) -> HashMap<char, usize> {
+    let mut counts = HashMap::new();
+    for c in crates.filter(|&c| c != ' ') {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// The instructions that get from one state to another, one per line in the same format the
+/// puzzle input uses
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Plan(pub Vec<Instr>);
+
+impl Display for Plan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, instr) in self.0.iter().enumerate() {
+            if idx != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{instr}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Why [`plan`] couldn't come up with a plan
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum PlanError {
+    /// The goal needs crates that aren't in the stacks, or stacks past the last one, or the
+    /// crane can't ever get there
+    Unreachable,
+    /// Every plan of up to this many moves was tried
+    TooManyMoves(usize),
+    /// The search found this many different states without getting there
+    TooManyStates(usize),
+}
+
+impl Display for PlanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanError::Unreachable => write!(f, "the goal can't be reached from these stacks"),
+            PlanError::TooManyMoves(max) => write!(f, "no plan within {max} moves"),
+            PlanError::TooManyStates(max) => write!(f, "gave up after {max} states"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+/// Breadth first search for the shortest list of instructions that takes `start` to `goal`
+/// with the given crane.  Gives up once plans would need more than `max_moves` instructions or
+/// more than `max_states` different states have turned up, since each move can lead to
+/// hundreds more on a full set of stacks.  The moves tried along the way go through a copy of
+/// the crane so `crane` is left untouched
+pub fn plan<C: Crane + Clone>(
+    start: &Boxes,
+    goal: &Goal,
+    crane: &C,
+    max_moves: usize,
+    max_states: usize,
+) -> Result<Plan, PlanError> {
+    let mut crane = crane.clone();
+    if goal.reached(start) {
+        return Ok(Plan::default());
+    }
+    if !goal.possible_from(start) {
+        return Err(PlanError::Unreachable);
+    }
+
+    let cols = start.cols();
+
+    // Every state we've found along with the state it came from and how we got there.  The
+    // states are shared with `seen` rather than copied
+    let mut states: Vec<(Rc<Boxes>, usize, Instr)> =
+        vec![(Rc::new(start.clone()), 0, Instr::default())];
+    let mut seen = HashSet::from([Rc::clone(&states[0].0)]);
+    let mut queue = VecDeque::from([(0, 0)]);
+    let mut cut_off = false;
+
+    while let Some((idx, depth)) = queue.pop_front() {
+        if depth == max_moves {
+            cut_off = true;
+            continue;
+        }
+
+        for src in 0..cols {
+            for dst in (0..cols).filter(|&dst| dst != src) {
+                for count in 1..=states[idx].0.stacks[src].len() {
+                    let instr = Instr::new(src, dst, count);
+                    let mut next = (*states[idx].0).clone();
+                    next.transfer(&instr, &mut crane);
+
+                    if seen.contains(&next) {
+                        continue;
+                    }
+                    if states.len() == max_states {
+                        return Err(PlanError::TooManyStates(max_states));
+                    }
+
+                    let done = goal.reached(&next);
+                    let next = Rc::new(next);
+                    seen.insert(Rc::clone(&next));
+                    states.push((next, idx, instr));
+
+                    if done {
+                        return Ok(unwind(&states));
+                    }
+                    queue.push_back((states.len() - 1, depth + 1));
+                }
+            }
+        }
+    }
+
+    if cut_off {
+        Err(PlanError::TooManyMoves(max_moves))
+    } else {
+        Err(PlanError::Unreachable)
+    }
+}
+
+/// Follows the last state back to the start to build up the plan
+fn unwind(states: &[(Rc<Boxes>, usize, Instr)]) -> Plan {
+    let mut instrs = Vec::new();
+
+    let mut idx = states.len() - 1;
+    while idx != 0 {
+        let (_, parent, instr) = &states[idx];
+        instrs.push(instr.clone());
+        idx = *parent;
+    }
+
+    instrs.reverse();
+    Plan(instrs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crane::{Cost, CostModel, CrateMover9000, CrateMover9001, MaxLift, Metered};
+    use crate::history::History;
+    use crate::tests::{make_instr, make_setup};
+
+    #[test]
+    fn test_plan_tops() {
+        let mut start = Boxes::default();
+        start.add_lines(&mut make_setup().lines());
+
+        // Every top has to change and a move only changes two of them, so two is the fewest
+        let goal = Goal::parse("CMZ");
+        let mut crane = Metered::new(CrateMover9000, CostModel::default());
+        let found = plan(&start, &goal, &crane, 4, 1000).unwrap();
+        assert_eq!(2, found.0.len());
+        assert_eq!(Cost::default(), crane.cost);
+
+        // Feed the plan back through the normal instruction parsing
+        let mut history = History::new(start.clone());
+        history
+            .run_all(&mut found.to_string().lines(), 1, &mut crane)
+            .unwrap();
+        assert_eq!("CMZ", history.boxes().answer());
+        assert_eq!(2, crane.cost.instrs);
+
+        assert_eq!(
+            Ok(Plan::default()),
+            plan(&start, &Goal::parse("NDP"), &CrateMover9000, 4, 1000)
+        );
+        assert_eq!(
+            Err(PlanError::TooManyMoves(1)),
+            plan(&start, &goal, &CrateMover9000, 1, 1000)
+        );
+        assert_eq!(
+            Err(PlanError::TooManyStates(5)),
+            plan(&start, &goal, &CrateMover9000, 4, 5)
+        );
+    }
+
+    #[test]
+    fn test_plan_unreachable() {
+        let mut start = Boxes::default();
+        start.add_lines(&mut make_setup().lines());
+
+        // Caught up front, before any states are tried
+        for goal in ["XYZ", "NNP", "CMZD"] {
+            assert_eq!(
+                Err(PlanError::Unreachable),
+                plan(&start, &Goal::parse(goal), &CrateMover9000, usize::MAX, 1),
+                "{goal}"
+            );
+        }
+
+        let mut want = start.clone();
+        want.stacks[2].push_back('X');
+        assert_eq!(
+            Err(PlanError::Unreachable),
+            plan(
+                &start,
+                &Goal::Stacks(Box::new(want)),
+                &CrateMover9000,
+                usize::MAX,
+                1
+            )
+        );
+    }
+
+    #[test]
+    fn test_plan_stacks() {
+        let mut start = Boxes::default();
+        start.add_lines(&mut make_setup().lines());
+
+        let mut want = start.clone();
+        want.run_all(&mut make_instr().lines(), 1, &mut CrateMover9001)
+            .unwrap();
+        assert_ne!(start, want);
+
+        let goal = Goal::parse(&want.to_string());
+        assert_eq!(Goal::Stacks(Box::new(want.clone())), goal);
+
+        // With either crane it takes four moves, and searching no deeper than three finds nothing
+        let moves = 4;
+        for max in [1, 3] {
+            let mut crane = MaxLift(max);
+            let found = plan(&start, &goal, &crane, 10, 100_000).unwrap();
+            assert_eq!(moves, found.0.len(), "{found}");
+            assert_eq!(
+                Err(PlanError::TooManyMoves(moves - 1)),
+                plan(&start, &goal, &crane, moves - 1, 100_000)
+            );
+
+            let mut history = History::new(start.clone());
+            history
+                .run_all(&mut found.to_string().lines(), 1, &mut crane)
+                .unwrap();
+            assert!(goal.reached(history.boxes()), "{found}");
+        }
+    }
+}
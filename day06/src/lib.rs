@@ -0,0 +1,211 @@
+pub mod scanner;
+
+use crate::scanner::{MarkerSpec, Mode, Scanner};
+use std::fmt::{Display, Formatter};
+use std::io::Read;
+
+/// Finds the end of the first run of `group_size` distinct bytes, reading the stream a byte at
+/// a time so it never needs to be held in memory
+pub fn solve<R: Read>(data: R, group_size: usize) -> Result<usize, MarkerError> {
+    let mut scanner = Scanner::new(data, &[MarkerSpec::new(group_size, Mode::First)?]);
+
+    match scanner.next() {
+        Some(marker) => Ok(marker?.end),
+        None => Err(MarkerError::NotFound {
+            group_size,
+            read: scanner.pos(),
+        }),
+    }
+}
+
+/// Same as [`solve`] but reads the stream as UTF-8 so multi-byte chars count once, returning
+/// the marker's end as both a char and a byte offset
+pub fn solve_chars<R: Read>(data: R, group_size: usize) -> Result<(usize, usize), MarkerError> {
+    let mut scanner = Scanner::chars(data, &[MarkerSpec::new(group_size, Mode::First)?]);
+
+    match scanner.next() {
+        Some(marker) => {
+            let marker = marker?;
+            Ok((marker.end, marker.byte_end))
+        }
+        None => Err(MarkerError::NotFound {
+            group_size,
+            read: scanner.byte_pos(),
+        }),
+    }
+}
+
+#[derive(Debug)]
+pub enum MarkerError {
+    Io(std::io::Error),
+    NotFound {
+        group_size: usize,
+        read: usize,
+    },
+    /// A marker has to be at least one character long
+    EmptyMarker,
+}
+
+impl Display for MarkerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarkerError::Io(e) => write!(f, "couldn't read the datastream: {e}"),
+            MarkerError::NotFound { group_size, read } => write!(
+                f,
+                "no marker of {group_size} distinct characters in {read} bytes"
+            ),
+            MarkerError::EmptyMarker => write!(f, "markers can't be empty"),
+        }
+    }
+}
+
+impl std::error::Error for MarkerError {}
+
+impl From<std::io::Error> for MarkerError {
+    fn from(e: std::io::Error) -> Self {
+        MarkerError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn examples() {
+        for (data, len, want) in [
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 4, 5),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 4, 6),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4, 10),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4, 11),
+        ] {
+            assert_eq!(want, solve(data.as_bytes(), len).unwrap());
+        }
+    }
+
+    #[test]
+    fn examples_14() {
+        for (data, want) in [
+            ("mjqjpqmgbljsphdztnvjfqwrcgsmlb", 19),
+            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 23),
+            ("nppdvjthqldpwncqszvftbrmjlhg", 23),
+            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 29),
+            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 26),
+        ] {
+            assert_eq!(want, solve(data.as_bytes(), 14).unwrap());
+        }
+    }
+
+    #[test]
+    fn scan_modes() {
+        let data = "abcdabcdaabcde";
+        let specs = [
+            MarkerSpec::new(4, Mode::First).unwrap(),
+            MarkerSpec::new(4, Mode::NonOverlapping).unwrap(),
+            MarkerSpec::new(4, Mode::Every).unwrap(),
+            MarkerSpec::new(5, Mode::Every).unwrap(),
+        ];
+
+        let found: Vec<(usize, usize)> = Scanner::new(data.as_bytes(), &specs)
+            .map(|m| m.map(|m| (m.spec, m.end)))
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                (0, 4),
+                (1, 4),
+                (2, 4),
+                (2, 5),
+                (2, 6),
+                (2, 7),
+                (1, 8),
+                (2, 8),
+                (2, 9),
+                (1, 13),
+                (2, 13),
+                (2, 14),
+                (3, 14),
+            ],
+            found
+        );
+    }
+
+    #[test]
+    fn scan_example_in_one_pass() {
+        let specs = [
+            MarkerSpec::new(4, Mode::First).unwrap(),
+            MarkerSpec::new(14, Mode::First).unwrap(),
+        ];
+
+        let ends: Vec<usize> = Scanner::new("mjqjpqmgbljsphdztnvjfqwrcgsmlb".as_bytes(), &specs)
+            .map(|m| m.unwrap().end)
+            .collect();
+        assert_eq!(vec![7, 19], ends);
+    }
+
+    #[test]
+    fn chars() {
+        let data = "αβγαδεζ";
+        assert_eq!((5, 10), solve_chars(data.as_bytes(), 4).unwrap());
+
+        // Every greek letter here starts with the same byte so the byte scanner never finds one
+        assert!(solve(data.as_bytes(), 4).is_err());
+
+        let specs = [MarkerSpec::new(3, Mode::Every).unwrap()];
+        let found: Vec<_> = Scanner::chars("aé✓a🦀".as_bytes(), &specs)
+            .map(|m| {
+                let m = m.unwrap();
+                (m.start(), m.end, m.byte_start, m.byte_end)
+            })
+            .collect();
+        assert_eq!(vec![(0, 3, 0, 6), (1, 4, 1, 7), (2, 5, 3, 11)], found);
+
+        assert!(solve_chars(&[b'a', 0xff, b'b'][..], 2).is_err());
+    }
+
+    #[test]
+    fn tokens() {
+        let specs = [MarkerSpec::new(3, Mode::First).unwrap()];
+        let words: Vec<_> = Scanner::tokens("a b a c d".split(' '), &specs)
+            .map(|m| m.unwrap().end)
+            .collect();
+        assert_eq!(vec![4], words);
+
+        let specs = [MarkerSpec::new(2, Mode::NonOverlapping).unwrap()];
+        let nums: Vec<_> = Scanner::tokens(vec![1, 1, 2, 3, 3, 3, 4], &specs)
+            .map(|m| m.unwrap().end)
+            .collect();
+        assert_eq!(vec![3, 7], nums);
+    }
+
+    #[test]
+    fn no_marker() {
+        let err = solve("abcabcabc".as_bytes(), 4).unwrap_err();
+        assert!(matches!(
+            err,
+            MarkerError::NotFound {
+                group_size: 4,
+                read: 9
+            }
+        ));
+        assert_eq!(
+            "no marker of 4 distinct characters in 9 bytes",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn empty_marker() {
+        assert!(matches!(
+            solve("abc".as_bytes(), 0),
+            Err(MarkerError::EmptyMarker)
+        ));
+        assert!(matches!(
+            solve_chars("abc".as_bytes(), 0),
+            Err(MarkerError::EmptyMarker)
+        ));
+        assert!(MarkerSpec::new(0, Mode::Every).is_err());
+        assert_eq!(1, MarkerSpec::new(1, Mode::Every).unwrap().size());
+    }
+}
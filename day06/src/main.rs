@@ -1,55 +1,55 @@
-use std::collections::HashSet;
+use day06::scanner::{MarkerSpec, Mode, Scanner};
+use day06::{solve, solve_chars, MarkerError};
 
 const PART_1: bool = false;
+/// Print every start-of-packet and start-of-message marker found in one pass over the input
+const SCAN_ALL: bool = false;
+/// Treat the input as UTF-8 chars rather than raw bytes
+const UNICODE: bool = false;
 
 static PART_1_DATA: &str = include_str!("input");
 
-fn main() {
+fn main() -> Result<(), MarkerError> {
+    if SCAN_ALL {
+        scan_all()?;
+    }
+
     if PART_1 {
-        part1();
+        part1()
     } else {
-        part2();
+        part2()
     }
 }
 
-fn part1() {
-    println!("{}", solve(PART_1_DATA, 4));
+fn part1() -> Result<(), MarkerError> {
+    print_marker(4)
 }
 
-fn part2() {
-    println!("{}", solve(PART_1_DATA, 14));
+fn part2() -> Result<(), MarkerError> {
+    print_marker(14)
 }
 
-fn solve(data: &str, group_size: usize) -> usize {
-    let mut dupes = HashSet::with_capacity(group_size * 2);
+fn print_marker(group_size: usize) -> Result<(), MarkerError> {
+    if UNICODE {
+        let (chars, bytes) = solve_chars(PART_1_DATA.as_bytes(), group_size)?;
+        println!("{chars} (byte {bytes})");
+    } else {
+        println!("{}", solve(PART_1_DATA.as_bytes(), group_size)?);
+    }
 
-    'WIND: for (idx, wind) in data.as_bytes().windows(group_size).enumerate() {
-        dupes.clear();
+    Ok(())
+}
 
-        for b in wind.iter() {
-            if !dupes.insert(*b) {
-                continue 'WIND;
-            }
-        }
+fn scan_all() -> Result<(), MarkerError> {
+    let specs = [
+        MarkerSpec::new(4, Mode::Every)?,
+        MarkerSpec::new(14, Mode::NonOverlapping)?,
+    ];
 
-        return idx + group_size;
+    for marker in Scanner::new(PART_1_DATA.as_bytes(), &specs) {
+        let marker = marker?;
+        println!("{:>2} @ {}..{}", marker.size, marker.start(), marker.end);
     }
-    panic!("no answer")
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn examples() {
-        for (data, len, want) in [
-            ("bvwbjplbgvbhsrlpgdmjqwftvncz", 4, 5),
-            ("nppdvjthqldpwncqszvftbrmjlhg", 4, 6),
-            ("nznrnfrfntjfmvfwmzdfjlvtqnbhcprsg", 4, 10),
-            ("zcfzfwzzqfrljwzlrfnpqdbhtmscgvjw", 4, 11),
-        ] {
-            assert_eq!(want, solve(data, len));
-        }
-    }
+    Ok(())
 }
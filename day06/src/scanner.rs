@@ -0,0 +1,332 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::io::{BufReader, Bytes, ErrorKind, Read};
+use std::iter::Map;
+
+use crate::MarkerError;
+
+/// Which occurrences of a marker to report
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Mode {
+    /// Only the first one, after which the marker isn't looked for anymore
+    First,
+    /// Every position where the last `size` symbols are all different, even if they overlap
+    Every,
+    /// Each marker only starts looking again once a whole new window has been read
+    NonOverlapping,
+}
+
+/// A marker to look for, which has to be at least one symbol long
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct MarkerSpec {
+    size: usize,
+    mode: Mode,
+}
+
+impl MarkerSpec {
+    pub fn new(size: usize, mode: Mode) -> Result<Self, MarkerError> {
+        if size == 0 {
+            return Err(MarkerError::EmptyMarker);
+        }
+
+        Ok(MarkerSpec { size, mode })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+}
+
+/// A marker that was found.  Positions are counted in symbols (bytes, chars or tokens depending
+/// on how the stream was read) as well as in bytes
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Marker {
+    /// Index of the spec that matched
+    pub spec: usize,
+    pub size: usize,
+    /// How many symbols had been read when the marker was complete (the puzzle's answer)
+    pub end: usize,
+    pub byte_start: usize,
+    pub byte_end: usize,
+}
+
+impl Marker {
+    /// Where the marker starts in the stream
+    pub fn start(&self) -> usize {
+        self.end - self.size
+    }
+}
+
+/// Keeps track of how many times each symbol shows up in a window
+pub trait Counts<T>: Default {
+    /// Adds one to the count for `t`, returning the new count
+    fn incr(&mut self, t: &T) -> usize;
+
+    /// Takes one from the count for `t`, returning the new count
+    fn decr(&mut self, t: &T) -> usize;
+}
+
+/// Plain per-byte counts, used when scanning raw bytes
+pub struct ByteCounts([usize; 256]);
+
+impl Default for ByteCounts {
+    fn default() -> Self {
+        ByteCounts([0; 256])
+    }
+}
+
+impl Counts<u8> for ByteCounts {
+    fn incr(&mut self, t: &u8) -> usize {
+        self.0[*t as usize] += 1;
+        self.0[*t as usize]
+    }
+
+    fn decr(&mut self, t: &u8) -> usize {
+        self.0[*t as usize] -= 1;
+        self.0[*t as usize]
+    }
+}
+
+impl<T: Hash + Eq + Clone> Counts<T> for HashMap<T, usize> {
+    fn incr(&mut self, t: &T) -> usize {
+        let count = self.entry(t.clone()).or_default();
+        *count += 1;
+        *count
+    }
+
+    fn decr(&mut self, t: &T) -> usize {
+        let count = self
+            .get_mut(t)
+            .expect("removing a symbol that was never added");
+        *count -= 1;
+
+        let left = *count;
+        if left == 0 {
+            self.remove(t);
+        }
+        left
+    }
+}
+
+/// Every symbol in the stream along with how many bytes it took up
+type Symbol<T> = std::io::Result<(T, usize)>;
+
+pub type ByteSymbols<R> = Map<Bytes<BufReader<R>>, fn(std::io::Result<u8>) -> Symbol<u8>>;
+pub type TokenSymbols<I, T> = Map<I, fn(T) -> Symbol<T>>;
+
+/// Looks for any number of markers in a single pass over a stream, yielding them in the order
+/// they finish.  Markers that finish on the same symbol come out in the order the specs were
+/// given
+pub struct Scanner<I, T, C = HashMap<T, usize>> {
+    symbols: I,
+    specs: Vec<(MarkerSpec, Window<T, C>, bool)>,
+    found: VecDeque<Marker>,
+    pos: usize,
+    byte_pos: usize,
+}
+
+impl<R: Read> Scanner<ByteSymbols<R>, u8, ByteCounts> {
+    /// Scans the raw bytes of a stream
+    pub fn new(data: R, specs: &[MarkerSpec]) -> Self {
+        fn one_byte(b: std::io::Result<u8>) -> Symbol<u8> {
+            b.map(|b| (b, 1))
+        }
+
+        Scanner::with_symbols(
+            BufReader::new(data).bytes().map(one_byte as fn(_) -> _),
+            specs,
+        )
+    }
+}
+
+impl<R: Read> Scanner<Utf8Chars<R>, char> {
+    /// Scans a UTF-8 stream a char at a time so multi-byte chars count as a single symbol
+    pub fn chars(data: R, specs: &[MarkerSpec]) -> Self {
+        Scanner::with_symbols(
+            Utf8Chars {
+                bytes: BufReader::new(data).bytes(),
+            },
+            specs,
+        )
+    }
+}
+
+impl<I: Iterator<Item = T>, T: Hash + Eq + Clone> Scanner<TokenSymbols<I, T>, T> {
+    /// Scans any stream of tokens.  There are no bytes to speak of so byte positions are the
+    /// same as token positions
+    pub fn tokens<II: IntoIterator<IntoIter = I>>(tokens: II, specs: &[MarkerSpec]) -> Self {
+        fn one_token<T>(t: T) -> Symbol<T> {
+            Ok((t, 1))
+        }
+
+        Scanner::with_symbols(tokens.into_iter().map(one_token as fn(_) -> _), specs)
+    }
+}
+
+impl<I, T, C> Scanner<I, T, C>
+where
+    I: Iterator<Item = Symbol<T>>,
+    C: Counts<T>,
+{
+    fn with_symbols(symbols: I, specs: &[MarkerSpec]) -> Self {
+        Scanner {
+            symbols,
+            specs: specs
+                .iter()
+                .map(|&spec| (spec, Window::new(spec.size), false))
+                .collect(),
+            found: VecDeque::with_capacity(specs.len()),
+            pos: 0,
+            byte_pos: 0,
+        }
+    }
+
+    /// How many symbols have been read so far
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// How many bytes have been read so far
+    pub fn byte_pos(&self) -> usize {
+        self.byte_pos
+    }
+}
+
+impl<I, T, C> Iterator for Scanner<I, T, C>
+where
+    I: Iterator<Item = Symbol<T>>,
+    T: Clone,
+    C: Counts<T>,
+{
+    type Item = std::io::Result<Marker>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.found.is_empty() {
+            // No point reading the rest of the stream if nothing is left to look for
+            if self.specs.iter().all(|(_, _, done)| *done) {
+                return None;
+            }
+
+            let (sym, len) = match self.symbols.next()? {
+                Ok(s) => s,
+                Err(e) => return Some(Err(e)),
+            };
+            self.pos += 1;
+            self.byte_pos += len;
+
+            for (idx, (spec, window, done)) in self.specs.iter_mut().enumerate() {
+                if *done || !window.push(sym.clone(), len) {
+                    continue;
+                }
+
+                self.found.push_back(Marker {
+                    spec: idx,
+                    size: spec.size,
+                    end: self.pos,
+                    byte_start: self.byte_pos - window.bytes,
+                    byte_end: self.byte_pos,
+                });
+
+                match spec.mode {
+                    Mode::First => *done = true,
+                    Mode::Every => (),
+                    Mode::NonOverlapping => window.clear(),
+                }
+            }
+        }
+
+        self.found.pop_front().map(Ok)
+    }
+}
+
+/// Decodes the chars of a UTF-8 stream without reading all of it in first
+pub struct Utf8Chars<R> {
+    bytes: Bytes<BufReader<R>>,
+}
+
+impl<R: Read> Iterator for Utf8Chars<R> {
+    type Item = Symbol<char>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let invalid = || std::io::Error::new(ErrorKind::InvalidData, "stream isn't valid UTF-8");
+
+        let first = match self.bytes.next()? {
+            Ok(b) => b,
+            Err(e) => return Some(Err(e)),
+        };
+
+        let len = match first.leading_ones() {
+            0 => 1,
+            n @ 2..=4 => n as usize,
+            _ => return Some(Err(invalid())),
+        };
+
+        let mut buf = [first, 0, 0, 0];
+        for slot in buf[1..len].iter_mut() {
+            *slot = match self.bytes.next() {
+                Some(Ok(b)) => b,
+                Some(Err(e)) => return Some(Err(e)),
+                None => return Some(Err(invalid())),
+            };
+        }
+
+        Some(match std::str::from_utf8(&buf[..len]) {
+            Ok(s) => Ok((s.chars().next().unwrap(), len)),
+            Err(_) => Err(invalid()),
+        })
+    }
+}
+
+/// The last `size` symbols of the stream along with how often each one shows up in them
+struct Window<T, C> {
+    size: usize,
+    symbols: VecDeque<(T, usize)>,
+    counts: C,
+    /// How many distinct symbols show up more than once in the window
+    dupes: usize,
+    /// How many bytes the symbols in the window take up
+    bytes: usize,
+}
+
+impl<T, C: Counts<T>> Window<T, C> {
+    fn new(size: usize) -> Self {
+        Window {
+            size,
+            symbols: VecDeque::with_capacity(size + 1),
+            counts: C::default(),
+            dupes: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Slides the window along by one symbol, returning true if the window is full and every
+    /// symbol in it is different
+    fn push(&mut self, sym: T, len: usize) -> bool {
+        if self.counts.incr(&sym) == 2 {
+            self.dupes += 1;
+        }
+        self.symbols.push_back((sym, len));
+        self.bytes += len;
+
+        if self.symbols.len() > self.size {
+            let (old, old_len) = self.symbols.pop_front().unwrap();
+            if self.counts.decr(&old) == 1 {
+                self.dupes -= 1;
+            }
+            self.bytes -= old_len;
+        }
+
+        self.symbols.len() == self.size && self.dupes == 0
+    }
+
+    fn clear(&mut self) {
+        self.symbols.clear();
+        self.counts = C::default();
+        self.dupes = 0;
+        self.bytes = 0;
+    }
+}
@@ -0,0 +1,331 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{Entry, FS};
+
+/// What the best plans should keep as small as possible.  Ties are broken by the other one
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum Minimize {
+    /// Delete as few bytes as possible
+    #[default]
+    Bytes,
+    /// Delete as few things as possible
+    Deletions,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct CleanupOpts {
+    pub minimize: Minimize,
+    /// The most directories and files a single plan may delete
+    pub max_deletions: usize,
+    /// How many plans to keep
+    pub plans: usize,
+    /// Only delete whole directories
+    pub dirs_only: bool,
+}
+
+impl Default for CleanupOpts {
+    fn default() -> Self {
+        Self {
+            minimize: Minimize::default(),
+            max_deletions: 3,
+            plans: 5,
+            dirs_only: false,
+        }
+    }
+}
+
+/// A set of directories and files, none inside another, that together free enough space
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct CleanupPlan {
+    pub paths: Vec<String>,
+    pub freed: i64,
+}
+
+impl CleanupPlan {
+    pub fn deletions(&self) -> usize {
+        self.paths.len()
+    }
+}
+
+impl Display for CleanupPlan {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {}: {}",
+            self.freed,
+            self.deletions(),
+            self.paths.join(" ")
+        )
+    }
+}
+
+/// Something that could be deleted.  `start..end` is the range of pre-order positions covered
+/// by it and everything under it, so two candidates are nested when one range holds the other
+struct Candidate {
+    path: String,
+    size: i64,
+    start: usize,
+    end: usize,
+}
+
+impl Candidate {
+    fn overlaps(&self, other: &Candidate) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+struct Search<'a> {
+    /// Biggest first
+    cands: &'a [Candidate],
+    /// `sums[idx]` is the total size of `cands[..idx]`
+    sums: Vec<i64>,
+    to_delete: i64,
+    opts: &'a CleanupOpts,
+    /// Indexes into `cands` along with how much they free, best first
+    best: Vec<(Vec<usize>, i64)>,
+}
+
+impl FS {
+    /// Ranks the ways of deleting directories and files so that at least `needed_space` is
+    /// free on a disk of `total_space`, best plan first.  A plan that needs nothing deleted is
+    /// returned on its own, and no plans at all means the disk can't be cleared enough within
+    /// `opts.max_deletions`
+    pub fn plan_cleanup(
+        &self,
+        total_space: i64,
+        needed_space: i64,
+        opts: &CleanupOpts,
+    ) -> Vec<CleanupPlan> {
+        let root_id = self.root_id();
+        let root_size = self.tree.get(&root_id).unwrap().data().size();
+
+        let to_delete = needed_space - (total_space - root_size);
+        if to_delete <= 0 {
+            return vec![CleanupPlan {
+                paths: Vec::new(),
+                freed: 0,
+            }];
+        }
+
+        // Number the nodes in pre-order so that each subtree is a contiguous range
+        let order: Vec<_> = self
+            .tree
+            .traverse_pre_order_ids(&root_id)
+            .unwrap()
+            .collect();
+        let mut ends = vec![0; order.len()];
+        let mut open: Vec<usize> = Vec::new();
+        for (idx, id) in order.iter().enumerate() {
+            let depth = self.tree.ancestor_ids(id).unwrap().count();
+            while open.len() > depth {
+                ends[open.pop().unwrap()] = idx;
+            }
+            open.push(idx);
+        }
+        for idx in open {
+            ends[idx] = order.len();
+        }
+
+        let mut cands: Vec<Candidate> = order
+            .iter()
+            .enumerate()
+            .filter_map(|(start, id)| {
+                let node = self.tree.get(id).unwrap();
+                let entry = node.data();
+                if opts.dirs_only && matches!(entry, Entry::File(_)) {
+                    return None;
+                }
+                // Anything that makes up all of its parent is the same plan as deleting the parent
+                if let Some(parent) = node.parent() {
+                    if self.tree.get(parent).unwrap().data().size() == entry.size() {
+                        return None;
+                    }
+                }
+                Some(Candidate {
+                    path: self.path(id),
+                    size: entry.size(),
+                    start,
+                    end: ends[start],
+                })
+            })
+            .collect();
+        cands.sort_by(|a, b| b.size.cmp(&a.size).then(a.start.cmp(&b.start)));
+
+        let mut sums = vec![0];
+        for cand in &cands {
+            sums.push(sums.last().unwrap() + cand.size);
+        }
+
+        let mut search = Search {
+            cands: &cands,
+            sums,
+            to_delete,
+            opts,
+            best: Vec::new(),
+        };
+        search.extend(&mut Vec::new(), 0, 0);
+
+        search
+            .best
+            .into_iter()
+            .map(|(picked, freed)| CleanupPlan {
+                paths: picked
+                    .into_iter()
+                    .map(|idx| cands[idx].path.clone())
+                    .collect(),
+                freed,
+            })
+            .collect()
+    }
+}
+
+impl Search<'_> {
+    /// Branch and bound over adding one more candidate from `from` onwards to `picked`
+    fn extend(&mut self, picked: &mut Vec<usize>, from: usize, freed: i64) {
+        let left = self.opts.max_deletions - picked.len();
+        if left == 0 {
+            return;
+        }
+
+        // Everything that would finish the plan on its own is at the front, so when this is the
+        // last pick go from the smallest of those upwards and stop as soon as it can't rank
+        if left == 1 {
+            let fits = self.cands[from..].partition_point(|c| freed + c.size >= self.to_delete);
+            for idx in (from..from + fits).rev() {
+                if !self.could_rank(picked.len() + 1, freed + self.cands[idx].size) {
+                    break;
+                }
+                self.try_pick(picked, idx, freed);
+            }
+            return;
+        }
+
+        for idx in from..self.cands.len() {
+            // Even the biggest of what's left can't free enough, and it only gets smaller
+            let most = self.sums[(idx + left).min(self.cands.len())] - self.sums[idx];
+            if freed + most < self.to_delete {
+                break;
+            }
+            if !self.could_rank(picked.len() + 1, freed + self.cands[idx].size) {
+                continue;
+            }
+            self.try_pick(picked, idx, freed);
+        }
+    }
+
+    fn try_pick(&mut self, picked: &mut Vec<usize>, idx: usize, freed: i64) {
+        let cand = &self.cands[idx];
+        if picked.iter().any(|&p| self.cands[p].overlaps(cand)) {
+            return;
+        }
+
+        let freed = freed + cand.size;
+        picked.push(idx);
+        if freed >= self.to_delete {
+            self.record(picked, freed);
+        } else {
+            self.extend(picked, idx + 1, freed);
+        }
+        picked.pop();
+    }
+
+    /// Whether a plan at least this big could still make it into the list
+    fn could_rank(&self, deletions: usize, freed: i64) -> bool {
+        match self.best.last() {
+            Some((worst, worst_freed)) if self.best.len() >= self.opts.plans => {
+                let minimize = self.opts.minimize;
+                key(minimize, deletions, freed) <= key(minimize, worst.len(), *worst_freed)
+            }
+            _ => self.opts.plans > 0,
+        }
+    }
+
+    fn record(&mut self, picked: &[usize], freed: i64) {
+        let mut picked = picked.to_vec();
+        picked.sort_by_key(|&idx| self.cands[idx].start);
+        self.best.push((picked, freed));
+
+        let (cands, minimize) = (self.cands, self.opts.minimize);
+        self.best.sort_by_cached_key(|(picked, freed)| {
+            let paths: Vec<&str> = picked.iter().map(|&idx| cands[idx].path.as_str()).collect();
+            (key(minimize, picked.len(), *freed), paths)
+        });
+        self.best.truncate(self.opts.plans);
+    }
+}
+
+fn key(minimize: Minimize, deletions: usize, freed: i64) -> (i64, i64) {
+    match minimize {
+        Minimize::Bytes => (freed, deletions as i64),
+        Minimize::Deletions => (deletions as i64, freed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_data;
+
+    #[test]
+    fn cleanup_plans() {
+        let fs = FS::new(test_data()).unwrap();
+        let plans = |opts: &CleanupOpts| -> Vec<(i64, Vec<String>)> {
+            fs.plan_cleanup(70_000_000, 30_000_000, opts)
+                .into_iter()
+                .map(|plan| (plan.freed, plan.paths))
+                .collect()
+        };
+        let paths = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (8504156, paths(&["/c.dat"])),
+                (9686326, paths(&["/d/j", "/d/d.ext"])),
+                (11274470, paths(&["/d/j", "/d/k"])),
+                (12093194, paths(&["/d/j", "/d/d.log"])),
+                (12840448, paths(&["/d/d.ext", "/d/k"])),
+            ],
+            plans(&CleanupOpts::default())
+        );
+
+        let opts = CleanupOpts {
+            minimize: Minimize::Deletions,
+            plans: 3,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![
+                (8504156, paths(&["/c.dat"])),
+                (14848514, paths(&["/b.txt"])),
+                (24933642, paths(&["/d"])),
+            ],
+            plans(&opts)
+        );
+
+        // The single directory answer is always one of the plans
+        let opts = CleanupOpts {
+            dirs_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            vec![(24933642, paths(&["/d"])), (48381165, paths(&["/"]))],
+            plans(&opts)
+        );
+        assert_eq!(fs.find_del_dir(70_000_000, 30_000_000), plans(&opts)[0].0);
+
+        // Nothing needs deleting
+        let plan = fs.plan_cleanup(100_000_000, 30_000_000, &CleanupOpts::default());
+        assert_eq!(
+            vec![CleanupPlan {
+                paths: vec![],
+                freed: 0
+            }],
+            plan
+        );
+
+        // Can't be done
+        assert!(fs
+            .plan_cleanup(50_000_000, 60_000_000, &CleanupOpts::default())
+            .is_empty());
+    }
+}
@@ -0,0 +1,233 @@
+use id_tree::NodeId;
+use serde::Serialize;
+use std::fmt::{Display, Formatter};
+
+use crate::{Entry, FS};
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+#[serde(tag = "change", rename_all = "lowercase")]
+pub enum ChangeKind {
+    /// Only in the newer snapshot, along with everything under it
+    Added { size: i64 },
+    /// Only in the older snapshot, along with everything under it
+    Removed { size: i64 },
+    /// A file in both with a different size
+    Resized { old: i64, new: i64 },
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+pub struct Change {
+    pub path: String,
+    pub dir: bool,
+    #[serde(flatten)]
+    pub kind: ChangeKind,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let slash = if self.dir && self.path != "/" {
+            "/"
+        } else {
+            ""
+        };
+
+        match self.kind {
+            ChangeKind::Added { size } => write!(f, "+ {}{slash} ({size})", self.path),
+            ChangeKind::Removed { size } => write!(f, "- {}{slash} ({size})", self.path),
+            ChangeKind::Resized { old, new } => {
+                write!(f, "~ {}{slash} {old} -> {new} ({:+})", self.path, new - old)
+            }
+        }
+    }
+}
+
+/// A directory in both snapshots whose total size changed
+#[derive(Eq, PartialEq, Clone, Debug, Serialize)]
+pub struct DirDelta {
+    pub path: String,
+    pub old: i64,
+    pub new: i64,
+    pub delta: i64,
+}
+
+impl Display for DirDelta {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} -> {} ({:+})",
+            self.path, self.old, self.new, self.delta
+        )
+    }
+}
+
+/// What changed between two snapshots.  Added and removed directories are reported once rather
+/// than for every entry under them, and a file that turned into a directory (or the other way
+/// round) is a removal and an addition.  Both lists are in pre-order, parents first
+#[derive(Eq, PartialEq, Clone, Debug, Default, Serialize)]
+pub struct Diff {
+    pub changes: Vec<Change>,
+    pub dirs: Vec<DirDelta>,
+}
+
+impl Diff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.dirs.is_empty()
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Couldn't serialize the diff")
+    }
+}
+
+impl Display for Diff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "no changes");
+        }
+
+        for change in &self.changes {
+            writeln!(f, "{change}")?;
+        }
+
+        if !self.dirs.is_empty() {
+            writeln!(f, "directory sizes:")?;
+            for dir in &self.dirs {
+                writeln!(f, "  {dir}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FS {
+    /// Everything that changed going from `self` to `newer`
+    pub fn diff(&self, newer: &FS) -> Diff {
+        let mut diff = Diff::default();
+        self.diff_dirs(&self.root_id(), newer, &newer.root_id(), &mut diff);
+        diff
+    }
+
+    fn diff_dirs(&self, id: &NodeId, newer: &FS, new_id: &NodeId, diff: &mut Diff) {
+        let (old_size, new_size) = (self.size(id), newer.size(new_id));
+        if old_size != new_size {
+            diff.dirs.push(DirDelta {
+                path: newer.path(new_id),
+                old: old_size,
+                new: new_size,
+                delta: new_size - old_size,
+            });
+        }
+
+        for child in self.tree.children_ids(id).unwrap() {
+            let name = self.tree.get(child).unwrap().data().name();
+            match newer.child(new_id, name) {
+                Some(new_child) => self.diff_entries(child, newer, &new_child, diff),
+                None => diff.changes.push(self.change(child, false)),
+            }
+        }
+
+        for new_child in newer.tree.children_ids(new_id).unwrap() {
+            let name = newer.tree.get(new_child).unwrap().data().name();
+            if self.child(id, name).is_none() {
+                diff.changes.push(newer.change(new_child, true));
+            }
+        }
+    }
+
+    fn diff_entries(&self, id: &NodeId, newer: &FS, new_id: &NodeId, diff: &mut Diff) {
+        let old = self.tree.get(id).unwrap().data();
+        let new = newer.tree.get(new_id).unwrap().data();
+
+        match (old, new) {
+            (Entry::Dir(_), Entry::Dir(_)) => self.diff_dirs(id, newer, new_id, diff),
+            (Entry::File(old), Entry::File(new)) => {
+                if old.size != new.size {
+                    diff.changes.push(Change {
+                        path: newer.path(new_id),
+                        dir: false,
+                        kind: ChangeKind::Resized {
+                            old: old.size,
+                            new: new.size,
+                        },
+                    });
+                }
+            }
+            _ => {
+                diff.changes.push(self.change(id, false));
+                diff.changes.push(newer.change(new_id, true));
+            }
+        }
+    }
+
+    fn size(&self, id: &NodeId) -> i64 {
+        self.tree.get(id).unwrap().data().size()
+    }
+
+    fn change(&self, id: &NodeId, added: bool) -> Change {
+        let size = self.size(id);
+        Change {
+            path: self.path(id),
+            dir: matches!(self.tree.get(id).unwrap().data(), Entry::Dir(_)),
+            kind: if added {
+                ChangeKind::Added { size }
+            } else {
+                ChangeKind::Removed { size }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_data;
+
+    #[test]
+    fn diff_snapshots() {
+        let old = FS::new(test_data()).unwrap();
+        assert!(old.diff(&old).is_empty());
+        assert_eq!("no changes\n", old.diff(&old).to_string());
+
+        let changed = format!(
+            "{}\n$ cd /a\n$ ls\ndir e\n29116 f\n3000 g\ndir x\n$ cd /d\n$ ls\n4060174 j\n\
+             8033020 d.log\ndir d.ext\n7214296 k\n$ cd d.ext\n$ ls\n1 z",
+            test_data()
+        );
+        let new = FS::new(&changed).unwrap();
+
+        let diff = old.diff(&new);
+        let want = "~ /a/g 2557 -> 3000 (+443)
+- /a/h.lst (62596)
++ /a/x/ (0)
+- /d/d.ext (5626152)
++ /d/d.ext/ (1)
+directory sizes:
+  / 48381165 -> 42692861 (-5688304)
+  /a 94853 -> 32700 (-62153)
+  /d 24933642 -> 19307491 (-5626151)
+";
+        assert_eq!(want, diff.to_string());
+
+        let back = new.diff(&old);
+        assert_eq!(diff.changes.len(), back.changes.len());
+        assert_eq!(
+            vec![5688304, 62153, 5626151],
+            back.dirs.iter().map(|d| d.delta).collect::<Vec<_>>()
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&diff.to_json()).unwrap();
+        assert_eq!(
+            serde_json::json!({"path": "/a/g", "dir": false, "change": "resized", "old": 2557, "new": 3000}),
+            json["changes"][0]
+        );
+        assert_eq!(
+            serde_json::json!({"path": "/d/d.ext", "dir": true, "change": "added", "size": 1}),
+            json["changes"][4]
+        );
+        assert_eq!(
+            serde_json::json!({"path": "/a", "old": 94853, "new": 32700, "delta": -62153}),
+            json["dirs"][1]
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use id_tree::NodeId;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::fs::{DirBuilder, File};
+use std::io;
+use std::path::Path;
+
+use crate::shell::Conflict;
+use crate::{Entry, FS};
+
+/// What gets written out for a whole [`FS`]
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+struct Export {
+    root: JsonEntry,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conflicts: Vec<Conflict>,
+}
+
+/// A single directory or file.  Directory sizes are written out as computed and checked against
+/// the files under them when read back in
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum JsonEntry {
+    Dir {
+        name: String,
+        size: i64,
+        /// Whether the contents were seen, otherwise `size` is only a lower bound
+        listed: bool,
+        children: Vec<JsonEntry>,
+    },
+    File {
+        name: String,
+        size: i64,
+    },
+}
+
+/// Why some JSON couldn't be turned back into an [`FS`]
+#[derive(Debug)]
+pub enum ImportError {
+    Json(serde_json::Error),
+    /// The top entry has to be a directory called `/`
+    BadRoot,
+    BadName(String),
+    Duplicate(String),
+    BadSize(String),
+    SizeMismatch {
+        path: String,
+        recorded: i64,
+        computed: i64,
+    },
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImportError::Json(e) => write!(f, "invalid JSON: {e}"),
+            ImportError::BadRoot => write!(f, "the root has to be a directory named /"),
+            ImportError::BadName(path) => write!(f, "{path}: not a valid name"),
+            ImportError::Duplicate(path) => write!(f, "{path}: listed twice"),
+            ImportError::BadSize(path) => write!(f, "{path}: negative size"),
+            ImportError::SizeMismatch {
+                path,
+                recorded,
+                computed,
+            } => write!(
+                f,
+                "{path}: recorded as {recorded} but the files under it add up to {computed}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(e: serde_json::Error) -> Self {
+        ImportError::Json(e)
+    }
+}
+
+impl FS {
+    /// The names, sizes and layout of everything, along with which directories were listed and
+    /// any conflicts, as pretty printed JSON that [`FS::from_json`] turns back into the same `FS`
+    pub fn to_json(&self) -> String {
+        let export = Export {
+            root: self.json_entry(&self.root_id()),
+            conflicts: self.conflicts.clone(),
+        };
+
+        serde_json::to_string_pretty(&export).expect("Couldn't serialize the filesystem")
+    }
+
+    fn json_entry(&self, id: &NodeId) -> JsonEntry {
+        match self.tree.get(id).unwrap().data() {
+            Entry::Dir(d) => JsonEntry::Dir {
+                name: d.name.clone(),
+                size: d.size,
+                listed: self.listed.contains(id),
+                children: self
+                    .tree
+                    .children_ids(id)
+                    .unwrap()
+                    .map(|child| self.json_entry(child))
+                    .collect(),
+            },
+            Entry::File(f) => JsonEntry::File {
+                name: f.name.clone(),
+                size: f.size,
+            },
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ImportError> {
+        let export: Export = serde_json::from_str(json)?;
+
+        let (size, listed, children) = match export.root {
+            JsonEntry::Dir {
+                name,
+                size,
+                listed,
+                children,
+            } if name == "/" => (size, listed, children),
+            _ => return Err(ImportError::BadRoot),
+        };
+
+        let mut fs = FS::empty();
+        fs.conflicts = export.conflicts;
+
+        let root_id = fs.root_id();
+        let mut recorded = vec![(root_id.clone(), size)];
+        if listed {
+            fs.listed.insert(root_id.clone());
+        }
+        fs.import_children(&root_id, children, &mut recorded)?;
+
+        FS::compute_dir_sizes(&mut fs.tree, root_id);
+
+        for (id, size) in recorded {
+            let computed = fs.tree.get(&id).unwrap().data().size();
+            if computed != size {
+                return Err(ImportError::SizeMismatch {
+                    path: fs.path(&id),
+                    recorded: size,
+                    computed,
+                });
+            }
+        }
+
+        Ok(fs)
+    }
+
+    /// Adds everything under `dir`, remembering the size each directory claimed to be
+    fn import_children(
+        &mut self,
+        dir: &NodeId,
+        children: Vec<JsonEntry>,
+        recorded: &mut Vec<(NodeId, i64)>,
+    ) -> Result<(), ImportError> {
+        for child in children {
+            let (JsonEntry::Dir { name, size, .. } | JsonEntry::File { name, size }) = &child;
+
+            let path = self.join(dir, name);
+            if FS::check_name(name).is_err() {
+                return Err(ImportError::BadName(path));
+            }
+            if self.child(dir, name).is_some() {
+                return Err(ImportError::Duplicate(path));
+            }
+            if *size < 0 {
+                return Err(ImportError::BadSize(path));
+            }
+
+            match child {
+                JsonEntry::Dir {
+                    name,
+                    size,
+                    listed,
+                    children,
+                } => {
+                    let id = self.insert(dir, Entry::new_dir(&name));
+                    if listed {
+                        self.listed.insert(id.clone());
+                    }
+                    recorded.push((id.clone(), size));
+                    self.import_children(&id, children, recorded)?;
+                }
+                JsonEntry::File { name, size } => {
+                    self.insert(dir, Entry::new_file(&name, size));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Recreates the tree under `target` with sparse files of the recorded sizes, so they take
+    /// up next to no real space.  `target` is created if needed but nothing already inside it is
+    /// ever overwritten
+    pub fn materialize(&self, target: &Path) -> io::Result<()> {
+        DirBuilder::new().recursive(true).create(target)?;
+        self.materialize_children(&self.root_id(), target)
+    }
+
+    fn materialize_children(&self, dir: &NodeId, target: &Path) -> io::Result<()> {
+        for child in self.tree.children_ids(dir).unwrap() {
+            let entry = self.tree.get(child).unwrap().data();
+            let path = target.join(entry.name());
+
+            match entry {
+                Entry::Dir(_) => {
+                    DirBuilder::new().create(&path)?;
+                    self.materialize_children(child, &path)?;
+                }
+                Entry::File(f) => File::create_new(&path)?.set_len(f.size as u64)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::ReportOpts;
+    use crate::tests::test_data;
+
+    #[test]
+    fn json_round_trip() {
+        let changed = format!(
+            "{}\n$ cd /a\n$ ls\n29116 f\n3000 g\n3000 g\ndir x\n$ cd x\n$ mkdir y",
+            test_data()
+        );
+
+        for lines in [
+            test_data(),
+            &changed,
+            "$ ls\ndir a\n10 b\n$ cd a\n$ mkdir c",
+        ] {
+            let fs = FS::new(lines).unwrap();
+            let json = fs.to_json();
+            let back = FS::from_json(&json).unwrap();
+
+            assert_eq!(fs, back);
+            assert_eq!(
+                fs.report(&ReportOpts::default()),
+                back.report(&ReportOpts::default())
+            );
+            assert_eq!(json, back.to_json());
+        }
+
+        let json = FS::new("$ ls\n5 a").unwrap().to_json();
+        let want = r#"{
+  "root": {
+    "type": "dir",
+    "name": "/",
+    "size": 5,
+    "listed": true,
+    "children": [
+      {
+        "type": "file",
+        "name": "a",
+        "size": 5
+      }
+    ]
+  }
+}"#;
+        assert_eq!(want, json);
+
+        assert_ne!(FS::new(test_data()).unwrap(), FS::from_json(&json).unwrap());
+    }
+
+    #[test]
+    fn json_import_errors() {
+        let import = |children: &str, size: i64| {
+            FS::from_json(&format!(
+                r#"{{"root": {{"type": "dir", "name": "/", "size": {size}, "listed": true, "children": [{children}]}}}}"#
+            ))
+        };
+        let file = |name: &str, size: i64| {
+            format!(r#"{{"type": "file", "name": "{name}", "size": {size}}}"#)
+        };
+
+        assert!(import(&file("a", 1), 1).is_ok());
+        assert!(matches!(
+            FS::from_json("{").unwrap_err(),
+            ImportError::Json(_)
+        ));
+        assert!(matches!(
+            FS::from_json(r#"{"root": {"type": "file", "name": "/", "size": 1}}"#).unwrap_err(),
+            ImportError::BadRoot
+        ));
+        assert!(matches!(
+            import(&file("a/b", 1), 1).unwrap_err(),
+            ImportError::BadName(p) if p == "/a/b"
+        ));
+        assert!(matches!(
+            import(&format!("{},{}", file("a", 1), file("a", 2)), 3).unwrap_err(),
+            ImportError::Duplicate(p) if p == "/a"
+        ));
+        assert!(matches!(
+            import(&file("a", -1), -1).unwrap_err(),
+            ImportError::BadSize(p) if p == "/a"
+        ));
+        assert!(matches!(
+            import(&file("a", 1), 2).unwrap_err(),
+            ImportError::SizeMismatch { path, recorded: 2, computed: 1 } if path == "/"
+        ));
+    }
+
+    #[test]
+    fn materialize_sparse() {
+        let target = std::env::temp_dir().join(format!("day07-materialize-{}", std::process::id()));
+        let fs = FS::new(test_data()).unwrap();
+
+        fs.materialize(&target).unwrap();
+        let size = |path: &str| std::fs::metadata(target.join(path)).unwrap().len();
+        assert_eq!(14848514, size("b.txt"));
+        assert_eq!(584, size("a/e/i"));
+        assert_eq!(7214296, size("d/k"));
+        assert!(std::fs::metadata(target.join("a/e")).unwrap().is_dir());
+
+        // Never overwrites anything
+        assert_eq!(
+            std::io::ErrorKind::AlreadyExists,
+            fs.materialize(&target).unwrap_err().kind()
+        );
+
+        std::fs::remove_dir_all(&target).unwrap();
+    }
+}
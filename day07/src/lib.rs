@@ -0,0 +1,245 @@
+pub mod cleanup;
+pub mod diff;
+pub mod export;
+pub mod query;
+pub mod report;
+pub mod shell;
+
+use crate::report::ReportOpts;
+use crate::shell::{Conflict, ShellError};
+use id_tree::{InsertBehavior, Node, NodeId, Tree};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
+pub enum Entry {
+    Dir(FsEntry),
+    File(FsEntry),
+}
+
+impl Entry {
+    pub fn size(&self) -> i64 {
+        match self {
+            Entry::Dir(d) => d.size,
+            Entry::File(d) => d.size,
+        }
+    }
+
+    pub fn dir_size(&self) -> Option<i64> {
+        match self {
+            Entry::Dir(d) => Some(d.size),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Entry::Dir(d) => write!(f, "Dir {} ({})", d.name, d.size),
+            Entry::File(d) => write!(f, "{} ({})", d.name, d.size),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Default)]
+pub struct FsEntry {
+    name: String,
+    size: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct FS {
+    tree: Tree<Entry>,
+    /// Directories whose contents we've seen, either from `ls` or from creating them
+    listed: HashSet<NodeId>,
+    conflicts: Vec<Conflict>,
+}
+
+/// Two filesystems are the same when they hold the same entries in the same order, with the same
+/// directories listed and the same conflicts.  Node ids depend on how the tree was built so they
+/// aren't compared
+impl PartialEq for FS {
+    fn eq(&self, other: &Self) -> bool {
+        self.conflicts == other.conflicts && self.same_as(&self.root_id(), other, &other.root_id())
+    }
+}
+
+impl FS {
+    pub fn new(lines: &str) -> Result<Self, ShellError> {
+        let mut tree = Self::empty();
+        tree.parse_cmds(lines)?;
+
+        let root_id = tree.root_id();
+        FS::compute_dir_sizes(&mut tree.tree, root_id);
+
+        Ok(tree)
+    }
+
+    /// Nothing but an unlisted `/`
+    fn empty() -> Self {
+        let mut tree = Self {
+            tree: Tree::new(),
+            listed: HashSet::new(),
+            conflicts: Vec::new(),
+        };
+
+        tree.tree
+            .insert(
+                Node::new(Entry::Dir(FsEntry {
+                    name: "/".to_string(),
+                    size: 0,
+                })),
+                InsertBehavior::AsRoot,
+            )
+            .expect("Couldn't insert root node");
+
+        tree
+    }
+
+    pub fn root_id(&self) -> NodeId {
+        self.tree.root_node_id().unwrap().clone()
+    }
+
+    /// A directory's size is only known if it and everything under it has been listed
+    pub fn size_known(&self, id: &NodeId) -> bool {
+        self.tree.traverse_pre_order_ids(id).unwrap().all(|id| {
+            match self.tree.get(&id).unwrap().data() {
+                Entry::Dir(_) => self.listed.contains(&id),
+                Entry::File(_) => true,
+            }
+        })
+    }
+
+    fn compute_dir_sizes(tree: &mut Tree<Entry>, entry: NodeId) {
+        #[allow(clippy::needless_collect)]
+        let to_check: Vec<NodeId> = tree
+            .children_ids(&entry)
+            .unwrap()
+            .filter(|&id| matches!(tree.get(id).unwrap().data(), Entry::Dir(_)))
+            .cloned()
+            .collect();
+
+        for id in to_check.into_iter() {
+            FS::compute_dir_sizes(tree, id)
+        }
+
+        let mut size = 0;
+        for child in tree.children(&entry).unwrap() {
+            size += child.data().size();
+        }
+
+        match tree.get_mut(&entry).unwrap().data_mut() {
+            Entry::Dir(e) => e.size = size,
+            _ => panic!("Setting size of file entry?"),
+        }
+    }
+
+    pub fn sum_dirs_under(&self, max_size: i64) -> i64 {
+        let mut sum = 0;
+
+        for entry in self
+            .tree
+            .traverse_level_order(self.tree.root_node_id().unwrap())
+            .unwrap()
+        {
+            match entry.data() {
+                Entry::Dir(e) if e.size <= max_size => sum += e.size,
+                _ => (),
+            }
+        }
+
+        sum
+    }
+
+    pub fn find_del_dir(&self, total_space: i64, needed_space: i64) -> i64 {
+        let root_id = self.tree.root_node_id().unwrap();
+        let root_size = self.tree.get(root_id).unwrap().data().dir_size().unwrap();
+
+        let free_space = total_space - root_size;
+        if free_space > needed_space {
+            return 0;
+        }
+
+        let to_delete = needed_space - free_space;
+        if root_size < to_delete {
+            panic!("Insufficient space");
+        }
+
+        let mut del_size = root_size;
+        for node in self.tree.traverse_level_order(root_id).unwrap() {
+            if let Some(size) = node.data().dir_size() {
+                if size >= to_delete {
+                    del_size = del_size.min(size);
+                }
+            }
+        }
+
+        del_size
+    }
+
+    fn same_as(&self, id: &NodeId, other: &FS, other_id: &NodeId) -> bool {
+        let children: Vec<&NodeId> = self.tree.children_ids(id).unwrap().collect();
+        let other_children: Vec<&NodeId> = other.tree.children_ids(other_id).unwrap().collect();
+
+        self.tree.get(id).unwrap().data() == other.tree.get(other_id).unwrap().data()
+            && self.listed.contains(id) == other.listed.contains(other_id)
+            && children.len() == other_children.len()
+            && children
+                .into_iter()
+                .zip(other_children)
+                .all(|(child, other_child)| self.same_as(child, other, other_child))
+    }
+
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    pub fn print(&self) {
+        print!("{}", self.report(&ReportOpts::default()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(crate) fn test_data() -> &'static str {
+        "$ cd /
+$ ls
+dir a
+14848514 b.txt
+8504156 c.dat
+dir d
+$ cd a
+$ ls
+dir e
+29116 f
+2557 g
+62596 h.lst
+$ cd e
+$ ls
+584 i
+$ cd ..
+$ cd ..
+$ cd d
+$ ls
+4060174 j
+8033020 d.log
+5626152 d.ext
+7214296 k"
+    }
+
+    #[test]
+    fn examples_1() {
+        let fs = FS::new(test_data()).unwrap();
+        fs.print();
+        assert_eq!(95_437, fs.sum_dirs_under(100_000))
+    }
+
+    #[test]
+    fn examples_2() {
+        let fs = FS::new(test_data()).unwrap();
+        assert_eq!(24_933_642, fs.find_del_dir(70_000_000, 30_000_000))
+    }
+}
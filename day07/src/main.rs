@@ -1,285 +1,232 @@
-use id_tree::{InsertBehavior, Node, NodeId, Tree};
-use std::fmt::{Display, Formatter};
+use day07::cleanup::{CleanupOpts, Minimize};
+use day07::query::Filter;
+use day07::report::{ReportOpts, SortBy, Style, TopKind};
+use day07::FS;
+use std::error::Error;
+use std::path::Path;
+use std::process::ExitCode;
 
 const PART_1: bool = false;
 
 static PART_1_DATA: &str = include_str!("input");
 
-fn main() {
-    let fs = FS::new(PART_1_DATA);
-
-    if false {
-        fs.print();
-    }
-
-    if PART_1 {
-        part1(fs);
-    } else {
-        part2(fs);
-    }
-}
-
-fn part1(fs: FS) {
-    println!("{}", fs.sum_dirs_under(100_000));
-}
-
-fn part2(fs: FS) {
-    println!("{}", fs.find_del_dir(70_000_000, 30_000_000));
-}
-
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
-enum Entry {
-    Dir(FsEntry),
-    File(FsEntry),
-}
-
-impl Entry {
-    fn size(&self) -> i64 {
-        match self {
-            Entry::Dir(d) => d.size,
-            Entry::File(d) => d.size,
-        }
-    }
-
-    fn dir_size(&self) -> Option<i64> {
-        match self {
-            Entry::Dir(d) => Some(d.size),
-            _ => None,
+const TOTAL_SPACE: i64 = 70_000_000;
+const NEEDED_SPACE: i64 = 30_000_000;
+
+const USAGE: &str = "usage: day07 [--input FILE|--import JSON] [COMMAND] [OPTIONS]
+
+COMMANDS:
+    tree, du             print a report of the whole filesystem
+    top N [dirs|files] [-h]
+                         print the N biggest directories or files
+    clean                rank the ways of freeing enough space for the update
+    export               print the filesystem as JSON, which --import reads back in
+    materialize DIR      recreate the filesystem under DIR using sparse files
+    diff FILE [--json]   show what changed going from this snapshot to FILE, which can be
+                         a transcript or an export
+    ls [PATH]            list a directory
+    find EXPR...         print everything that matches a filter, like `file size>1M or ext=log`
+
+OPTIONS:
+    -d, --max-depth N    only show N levels below /
+    -s, --sort KEY       sort by `size` or `name` instead of listing order
+    -h, --human          show sizes as 1.2K, 34M, ...
+    -p, --full-paths     show full paths in tree mode
+    -%, --percent        show each size as a percent of its parent
+    -a, --all            show files in du mode
+
+CLEAN OPTIONS:
+    --by KEY             rank plans by `bytes` deleted or number of `deletions`
+    -n, --plans N        show the best N plans
+    -m, --max-deletions N  delete at most N things in one plan
+    --dirs-only          only delete whole directories";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
         }
     }
 }
 
-impl Display for Entry {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Entry::Dir(d) => write!(f, "Dir {} ({})", d.name, d.size),
-            Entry::File(d) => write!(f, "{} ({})", d.name, d.size),
-        }
-    }
-}
-
-#[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug, Default)]
-struct FsEntry {
-    name: String,
-    size: i64,
-}
-
-#[derive(PartialEq, Clone, Debug)]
-struct FS {
-    tree: Tree<Entry>,
-}
-
-impl FS {
-    pub fn new(lines: &str) -> Self {
-        let mut tree = Self { tree: Tree::new() };
-
-        tree.tree
-            .insert(
-                Node::new(Entry::Dir(FsEntry {
-                    name: "/".to_string(),
-                    size: 0,
-                })),
-                InsertBehavior::AsRoot,
-            )
-            .expect("Couldn't insert root node");
+fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
 
-        tree.parse_cmds(lines);
-
-        let root_id = tree.tree.root_node_id().unwrap().clone();
-        FS::compute_dir_sizes(&mut tree.tree, root_id);
+    let fs = if let Some(path) = take_arg(&mut args, "--import")? {
+        FS::from_json(&std::fs::read_to_string(path)?)?
+    } else if let Some(path) = take_arg(&mut args, "--input")? {
+        FS::new(&std::fs::read_to_string(path)?)?
+    } else {
+        FS::new(PART_1_DATA)?
+    };
+    let json = take_flag(&mut args, "--json");
 
-        tree
+    for conflict in fs.conflicts() {
+        eprintln!("{conflict}");
     }
 
-    fn parse_cmds(&mut self, lines: &str) {
-        let tree = &mut self.tree;
-
-        let root_id = tree.root_node_id().unwrap().clone();
-        let mut cur_id = root_id.clone();
-
-        for line in lines.lines() {
-            if let Some(cmd) = line.strip_prefix("$ ") {
-                if let Some(dir) = cmd.strip_prefix("cd ") {
-                    cur_id = match dir {
-                        "/" => root_id.clone(),
-                        ".." => tree.get(&cur_id).unwrap().parent().unwrap().clone(),
-                        _ => FS::subdir(tree, &cur_id, dir),
-                    };
-                } else {
-                    match cmd {
-                        "ls" => (),
-                        _ => panic!("Unknown command: {cmd}"),
-                    }
-                }
-            } else if let Some(dir) = line.strip_prefix("dir ") {
-                // Add empty subdir
-                FS::subdir(tree, &cur_id, dir);
+    match args.first() {
+        None => {
+            if PART_1 {
+                part1(fs);
             } else {
-                let (num, idx) = atoi::FromRadix10::from_radix_10(line.as_bytes());
-                if idx == 0 {
-                    panic!("Unknown entry: {line}")
-                }
-
-                let node = Node::new(Entry::File(FsEntry {
-                    name: line[idx + 1..].to_string(),
-                    size: num,
-                }));
-
-                tree.insert(node, InsertBehavior::UnderNode(&cur_id))
-                    .unwrap();
+                part2(fs);
             }
         }
-    }
 
-    fn subdir(tree: &mut Tree<Entry>, cur_id: &NodeId, name: &str) -> NodeId {
-        let node_id = tree
-            .get(cur_id)
-            .unwrap()
-            .children()
-            .iter()
-            .find(|&id| match tree.get(id).unwrap().data() {
-                Entry::Dir(fe) if fe.name == name => true,
-
-                Entry::File(fe) if fe.name == name => {
-                    panic!("Tried CDing to the file {name}")
+        Some(&"top") => {
+            let n = args.get(1).ok_or(USAGE)?.parse()?;
+            // The kind is optional so anything else has to be `-h`, the only option it uses
+            let (kind, rest) = match args.get(2) {
+                Some(&"files") => (TopKind::Files, 3),
+                Some(&"dirs") => (TopKind::Dirs, 3),
+                _ => (TopKind::Dirs, 2),
+            };
+            let mut opts = ReportOpts::default();
+            for &arg in &args[args.len().min(rest)..] {
+                match arg {
+                    "-h" | "--human" => opts.human = true,
+                    _ => return Err(USAGE.into()),
                 }
-                _ => false,
-            })
-            .cloned();
-
-        node_id.unwrap_or_else(|| {
-            let node = Node::new(Entry::Dir(FsEntry {
-                name: name.to_string(),
-                size: 0,
-            }));
-
-            tree.insert(node, InsertBehavior::UnderNode(cur_id))
-                .unwrap()
-        })
-    }
-
-    fn compute_dir_sizes(tree: &mut Tree<Entry>, entry: NodeId) {
-        #[allow(clippy::needless_collect)]
-        let to_check: Vec<NodeId> = tree
-            .children_ids(&entry)
-            .unwrap()
-            .filter(|&id| matches!(tree.get(id).unwrap().data(), Entry::Dir(_)))
-            .cloned()
-            .collect();
-
-        for id in to_check.into_iter() {
-            FS::compute_dir_sizes(tree, id)
-        }
+            }
 
-        let mut size = 0;
-        for child in tree.children(&entry).unwrap() {
-            size += child.data().size();
+            for (path, size) in fs.top(n, kind) {
+                println!("{}\t{path}", opts.size(size));
+            }
         }
 
-        match tree.get_mut(&entry).unwrap().data_mut() {
-            Entry::Dir(e) => e.size = size,
-            _ => panic!("Setting size of file entry?"),
-        }
-    }
+        Some(&"clean") => {
+            let opts = parse_clean_opts(&args[1..])?;
+            let plans = fs.plan_cleanup(TOTAL_SPACE, NEEDED_SPACE, &opts);
+            if plans.is_empty() {
+                return Err("no plan frees enough space".into());
+            }
 
-    fn sum_dirs_under(&self, max_size: i64) -> i64 {
-        let mut sum = 0;
-
-        for entry in self
-            .tree
-            .traverse_level_order(self.tree.root_node_id().unwrap())
-            .unwrap()
-        {
-            match entry.data() {
-                Entry::Dir(e) if e.size <= max_size => sum += e.size,
-                _ => (),
+            for (rank, plan) in plans.iter().enumerate() {
+                println!("{}. {plan}", rank + 1);
             }
         }
 
-        sum
-    }
-
-    fn find_del_dir(&self, total_space: i64, needed_space: i64) -> i64 {
-        let root_id = self.tree.root_node_id().unwrap();
-        let root_size = self.tree.get(root_id).unwrap().data().dir_size().unwrap();
-
-        let free_space = total_space - root_size;
-        if free_space > needed_space {
-            return 0;
+        Some(&"ls") => {
+            let items = fs.list(args.get(1).unwrap_or(&"/"));
+            for item in items.map_err(|e| e.to_string())? {
+                println!("{item}");
+            }
         }
 
-        let to_delete = needed_space - free_space;
-        if root_size < to_delete {
-            panic!("Insufficient space");
+        Some(&"find") => {
+            let filter = Filter::parse(&args[1..].join(" "))?;
+            for item in fs.find(&filter) {
+                println!("{item}");
+            }
         }
 
-        let mut del_size = root_size;
-        for node in self.tree.traverse_level_order(root_id).unwrap() {
-            if let Some(size) = node.data().dir_size() {
-                if size >= to_delete {
-                    del_size = del_size.min(size);
-                }
+        Some(&"diff") => {
+            let newer = load(args.get(1).ok_or(USAGE)?)?;
+            let diff = fs.diff(&newer);
+            if json {
+                println!("{}", diff.to_json());
+            } else {
+                print!("{diff}");
             }
         }
 
-        del_size
+        Some(&"export") => println!("{}", fs.to_json()),
+
+        Some(&"materialize") => fs.materialize(Path::new(args.get(1).ok_or(USAGE)?))?,
+
+        Some(_) => print!("{}", fs.report(&parse_opts(&args)?)),
     }
 
-    fn print(&self) {
-        let root_id = self.tree.root_node_id().unwrap();
-        FS::print_lvl(&self.tree, root_id, 0)
+    Ok(())
+}
+
+/// Reads a snapshot from either a transcript or an export, going by whether it looks like JSON
+fn load(path: &str) -> Result<FS, Box<dyn Error>> {
+    let data = std::fs::read_to_string(path)?;
+    if data.trim_start().starts_with('{') {
+        Ok(FS::from_json(&data)?)
+    } else {
+        Ok(FS::new(&data)?)
     }
+}
 
-    fn print_lvl(tree: &Tree<Entry>, id: &NodeId, level: usize) {
-        let node = tree.get(id).unwrap().data();
-        println!("{}{}", " ".repeat(level * 2), node);
+/// Pulls `flag` out of the arguments, returning whether it was there
+fn take_flag(args: &mut Vec<&str>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|&a| a != flag);
+    args.len() != before
+}
 
-        for child in tree.children_ids(id).unwrap() {
-            FS::print_lvl(tree, child, level + 1);
+/// Pulls `flag` and the value after it out of the arguments
+fn take_arg(args: &mut Vec<&str>, flag: &str) -> Result<Option<String>, Box<dyn Error>> {
+    match args.iter().position(|&a| a == flag) {
+        Some(idx) => {
+            let value = args.get(idx + 1).ok_or(USAGE)?.to_string();
+            args.drain(idx..idx + 2);
+            Ok(Some(value))
         }
+        None => Ok(None),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    fn test_data() -> &'static str {
-        "$ cd /
-$ ls
-dir a
-14848514 b.txt
-8504156 c.dat
-dir d
-$ cd a
-$ ls
-dir e
-29116 f
-2557 g
-62596 h.lst
-$ cd e
-$ ls
-584 i
-$ cd ..
-$ cd ..
-$ cd d
-$ ls
-4060174 j
-8033020 d.log
-5626152 d.ext
-7214296 k"
-    }
+fn part1(fs: FS) {
+    println!("{}", fs.sum_dirs_under(100_000));
+}
+
+fn part2(fs: FS) {
+    println!("{}", fs.find_del_dir(TOTAL_SPACE, NEEDED_SPACE));
+}
 
-    #[test]
-    fn examples_1() {
-        let fs = FS::new(test_data());
-        fs.print();
-        assert_eq!(95_437, fs.sum_dirs_under(100_000))
+fn parse_opts(args: &[&str]) -> Result<ReportOpts, Box<dyn Error>> {
+    let mut opts = ReportOpts::default();
+
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        match arg {
+            "tree" => opts.style = Style::Tree,
+            "du" => opts.style = Style::Du,
+            "-d" | "--max-depth" => opts.max_depth = Some(args.next().ok_or(USAGE)?.parse()?),
+            "-s" | "--sort" => {
+                opts.sort = match *args.next().ok_or(USAGE)? {
+                    "size" => SortBy::Size,
+                    "name" => SortBy::Name,
+                    _ => return Err(USAGE.into()),
+                }
+            }
+            "-h" | "--human" => opts.human = true,
+            "-p" | "--full-paths" => opts.full_paths = true,
+            "-%" | "--percent" => opts.percent = true,
+            "-a" | "--all" => opts.all = true,
+            _ => return Err(USAGE.into()),
+        }
     }
 
-    #[test]
-    fn examples_2() {
-        let fs = FS::new(test_data());
-        assert_eq!(24_933_642, fs.find_del_dir(70_000_000, 30_000_000))
+    Ok(opts)
+}
+
+fn parse_clean_opts(args: &[&str]) -> Result<CleanupOpts, Box<dyn Error>> {
+    let mut opts = CleanupOpts::default();
+
+    let mut args = args.iter();
+    while let Some(&arg) = args.next() {
+        match arg {
+            "--by" => {
+                opts.minimize = match *args.next().ok_or(USAGE)? {
+                    "bytes" => Minimize::Bytes,
+                    "deletions" => Minimize::Deletions,
+                    _ => return Err(USAGE.into()),
+                }
+            }
+            "-n" | "--plans" => opts.plans = args.next().ok_or(USAGE)?.parse()?,
+            "-m" | "--max-deletions" => opts.max_deletions = args.next().ok_or(USAGE)?.parse()?,
+            "--dirs-only" => opts.dirs_only = true,
+            _ => return Err(USAGE.into()),
+        }
     }
+
+    Ok(opts)
 }
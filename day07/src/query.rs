@@ -0,0 +1,464 @@
+use id_tree::NodeId;
+use std::fmt::{Display, Formatter};
+
+use crate::shell::ErrorKind;
+use crate::{Entry, FS};
+
+/// A directory or file found by a query, named by its full path
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Item {
+    pub path: String,
+    pub size: i64,
+    pub is_dir: bool,
+}
+
+impl Item {
+    pub fn name(&self) -> &str {
+        match self.path.rsplit_once('/') {
+            Some((_, "")) | None => &self.path,
+            Some((_, name)) => name,
+        }
+    }
+}
+
+impl Display for Item {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}\t{}", self.size, self.path)?;
+        if self.is_dir && self.path != "/" {
+            write!(f, "/")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Cmp {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
+
+impl Cmp {
+    pub fn test(self, have: i64, want: i64) -> bool {
+        match self {
+            Cmp::Lt => have < want,
+            Cmp::Le => have <= want,
+            Cmp::Eq => have == want,
+            Cmp::Ne => have != want,
+            Cmp::Ge => have >= want,
+            Cmp::Gt => have > want,
+        }
+    }
+}
+
+/// Which items a query keeps.  Globs understand `*` for any run of characters and `?` for any
+/// single one
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Filter {
+    Any,
+    Dirs,
+    Files,
+    /// The name matches a glob
+    Name(String),
+    /// The full path matches a glob, where `*` can cross a `/`
+    Path(String),
+    /// The name ends in `.` and then this
+    Ext(String),
+    Size(Cmp, i64),
+    /// Somewhere under this directory
+    Under(String),
+    Not(Box<Filter>),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+}
+
+/// Why a filter expression couldn't be parsed
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum FilterError {
+    Empty,
+    UnknownTerm(String),
+    BadSize(String),
+    Unbalanced,
+    /// `not` or `or` with nothing after it
+    Dangling(&'static str),
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FilterError::Empty => write!(f, "empty filter"),
+            FilterError::UnknownTerm(term) => write!(f, "unknown term `{term}`"),
+            FilterError::BadSize(size) => write!(f, "`{size}` isn't a valid size"),
+            FilterError::Unbalanced => write!(f, "unbalanced parentheses"),
+            FilterError::Dangling(op) => write!(f, "nothing after `{op}`"),
+        }
+    }
+}
+
+impl std::error::Error for FilterError {}
+
+impl Filter {
+    /// Parses expressions like `file ext=log size>1M or (dir not under=/a)`.  Terms next to each
+    /// other must all match, `or` binds looser than that and `not` (or a `!` in front of a term)
+    /// tighter.
+    ///
+    /// The terms are `dir`, `file`, `name=GLOB`, `path=GLOB`, `ext=EXT`, `under=DIR` and `size`
+    /// followed by one of `< <= = != >= >` and a number with an optional `K`, `M` or `G`
+    pub fn parse(expr: &str) -> Result<Self, FilterError> {
+        let spaced = expr.replace('(', " ( ").replace(')', " ) ");
+        let tokens: Vec<&str> = spaced.split_whitespace().collect();
+        if tokens.is_empty() {
+            return Err(FilterError::Empty);
+        }
+
+        let mut pos = 0;
+        let filter = Filter::parse_or(&tokens, &mut pos)?;
+        if pos != tokens.len() {
+            return Err(FilterError::Unbalanced);
+        }
+
+        Ok(filter)
+    }
+
+    fn parse_or(tokens: &[&str], pos: &mut usize) -> Result<Self, FilterError> {
+        let mut any = vec![Filter::parse_and(tokens, pos)?];
+
+        while tokens.get(*pos) == Some(&"or") {
+            *pos += 1;
+            if matches!(tokens.get(*pos), None | Some(&")")) {
+                return Err(FilterError::Dangling("or"));
+            }
+            any.push(Filter::parse_and(tokens, pos)?);
+        }
+
+        Ok(if any.len() == 1 {
+            any.pop().unwrap()
+        } else {
+            Filter::Or(any)
+        })
+    }
+
+    fn parse_and(tokens: &[&str], pos: &mut usize) -> Result<Self, FilterError> {
+        let mut all = Vec::new();
+
+        while let Some(&token) = tokens.get(*pos) {
+            match token {
+                "or" | ")" => break,
+                "and" => *pos += 1,
+                _ => all.push(Filter::parse_not(tokens, pos)?),
+            }
+        }
+
+        match all.len() {
+            0 => Err(FilterError::Empty),
+            1 => Ok(all.pop().unwrap()),
+            _ => Ok(Filter::And(all)),
+        }
+    }
+
+    fn parse_not(tokens: &[&str], pos: &mut usize) -> Result<Self, FilterError> {
+        let token = tokens[*pos];
+        *pos += 1;
+
+        match token {
+            "not" | "!" => match tokens.get(*pos) {
+                None | Some(&")") | Some(&"or") => Err(FilterError::Dangling("not")),
+                Some(_) => Ok(Filter::Not(Box::new(Filter::parse_not(tokens, pos)?))),
+            },
+            "(" => {
+                let inner = Filter::parse_or(tokens, pos)?;
+                if tokens.get(*pos) != Some(&")") {
+                    return Err(FilterError::Unbalanced);
+                }
+                *pos += 1;
+                Ok(inner)
+            }
+            _ => match token.strip_prefix('!') {
+                Some(term) => Ok(Filter::Not(Box::new(Filter::parse_term(term)?))),
+                None => Filter::parse_term(token),
+            },
+        }
+    }
+
+    fn parse_term(term: &str) -> Result<Self, FilterError> {
+        match term {
+            "dir" => return Ok(Filter::Dirs),
+            "file" => return Ok(Filter::Files),
+            "any" => return Ok(Filter::Any),
+            _ => (),
+        }
+
+        if let Some(size) = term.strip_prefix("size") {
+            let (cmp, size) = [
+                ("<=", Cmp::Le),
+                (">=", Cmp::Ge),
+                ("!=", Cmp::Ne),
+                ("<", Cmp::Lt),
+                (">", Cmp::Gt),
+                ("=", Cmp::Eq),
+            ]
+            .into_iter()
+            .find_map(|(op, cmp)| size.strip_prefix(op).map(|size| (cmp, size)))
+            .ok_or_else(|| FilterError::UnknownTerm(term.to_string()))?;
+
+            return Ok(Filter::Size(cmp, parse_size(size)?));
+        }
+
+        match term.split_once('=') {
+            Some(("name", glob)) => Ok(Filter::Name(glob.to_string())),
+            Some(("path", glob)) => Ok(Filter::Path(glob.to_string())),
+            Some(("ext", ext)) => Ok(Filter::Ext(ext.trim_start_matches('.').to_string())),
+            Some(("under", dir)) => Ok(Filter::Under(dir.trim_end_matches('/').to_string())),
+            _ => Err(FilterError::UnknownTerm(term.to_string())),
+        }
+    }
+
+    pub fn matches(&self, item: &Item) -> bool {
+        match self {
+            Filter::Any => true,
+            Filter::Dirs => item.is_dir,
+            Filter::Files => !item.is_dir,
+            Filter::Name(pattern) => glob(pattern, item.name()),
+            Filter::Path(pattern) => glob(pattern, &item.path),
+            Filter::Ext(ext) => item
+                .name()
+                .rsplit_once('.')
+                .is_some_and(|(_, have)| have == ext),
+            Filter::Size(cmp, size) => cmp.test(item.size, *size),
+            Filter::Under(dir) => item
+                .path
+                .strip_prefix(dir.as_str())
+                .is_some_and(|rest| rest.starts_with('/') && rest.len() > 1),
+            Filter::Not(filter) => !filter.matches(item),
+            Filter::And(all) => all.iter().all(|f| f.matches(item)),
+            Filter::Or(any) => any.iter().any(|f| f.matches(item)),
+        }
+    }
+}
+
+/// Reads a size like `584`, `10K` or `1.5M`, in powers of 1024 like [`crate::report::human_size`]
+fn parse_size(size: &str) -> Result<i64, FilterError> {
+    let bad = || FilterError::BadSize(size.to_string());
+
+    let (num, scale) = match size.char_indices().last().ok_or_else(bad)? {
+        (idx, 'k' | 'K') => (&size[..idx], 1 << 10),
+        (idx, 'm' | 'M') => (&size[..idx], 1 << 20),
+        (idx, 'g' | 'G') => (&size[..idx], 1 << 30),
+        _ => (size, 1),
+    };
+
+    if scale == 1 {
+        num.parse().map_err(|_| bad())
+    } else {
+        let num: f64 = num.parse().map_err(|_| bad())?;
+        if !num.is_finite() || num < 0.0 {
+            return Err(bad());
+        }
+        Ok((num * scale as f64) as i64)
+    }
+}
+
+/// Matches `text` against a pattern where `*` is any run of characters and `?` is any one
+pub fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was and how much text it has eaten so far
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                // Let the last `*` take one more character and try again
+                Some((star_p, star_t)) => {
+                    star = Some((star_p, star_t + 1));
+                    p = star_p + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+impl FS {
+    fn item(&self, id: &NodeId) -> Item {
+        let entry = self.tree.get(id).unwrap().data();
+        Item {
+            path: self.path(id),
+            size: entry.size(),
+            is_dir: matches!(entry, Entry::Dir(_)),
+        }
+    }
+
+    /// The directory or file at an absolute path
+    pub fn lookup(&self, path: &str) -> Result<Item, ErrorKind> {
+        Ok(self.item(&self.resolve(&self.root_id(), path)?))
+    }
+
+    /// Everything directly inside the directory at `path`, in listing order
+    pub fn list(&self, path: &str) -> Result<Vec<Item>, ErrorKind> {
+        let id = self.resolve(&self.root_id(), path)?;
+        self.dir(&id)?;
+
+        Ok(self
+            .tree
+            .children_ids(&id)
+            .unwrap()
+            .map(|child| self.item(child))
+            .collect())
+    }
+
+    /// Everything that matches `filter`, parents before their children
+    pub fn find(&self, filter: &Filter) -> Vec<Item> {
+        self.tree
+            .traverse_pre_order_ids(&self.root_id())
+            .unwrap()
+            .map(|id| self.item(&id))
+            .filter(|item| filter.matches(item))
+            .collect()
+    }
+
+    /// Files whose name matches `pattern`, or whose full path does if `pattern` has a `/` in it
+    pub fn find_glob(&self, pattern: &str) -> Vec<Item> {
+        let glob = if pattern.contains('/') {
+            Filter::Path(pattern.to_string())
+        } else {
+            Filter::Name(pattern.to_string())
+        };
+
+        self.find(&Filter::And(vec![Filter::Files, glob]))
+    }
+
+    pub fn find_ext(&self, ext: &str) -> Vec<Item> {
+        let ext = Filter::Ext(ext.trim_start_matches('.').to_string());
+        self.find(&Filter::And(vec![Filter::Files, ext]))
+    }
+
+    pub fn dirs_where(&self, pred: impl Fn(i64) -> bool) -> Vec<Item> {
+        self.find(&Filter::Dirs)
+            .into_iter()
+            .filter(|item| pred(item.size))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_data;
+
+    #[test]
+    fn query_paths() {
+        let fs = FS::new(test_data()).unwrap();
+
+        let item = |path: &str, size: i64, is_dir: bool| Item {
+            path: path.to_string(),
+            size,
+            is_dir,
+        };
+
+        assert_eq!(item("/a/e/i", 584, false), fs.lookup("/a/e/i").unwrap());
+        assert_eq!(item("/d", 24933642, true), fs.lookup("/d/").unwrap());
+        assert_eq!(item("/", 48381165, true), fs.lookup("/").unwrap());
+        assert_eq!(
+            ErrorKind::NotFound("/a/x".to_string()),
+            fs.lookup("/a/x").unwrap_err()
+        );
+
+        assert_eq!(vec![item("/a/e/i", 584, false)], fs.list("/a/e").unwrap());
+        assert_eq!(
+            ErrorKind::NotADir("/b.txt".to_string()),
+            fs.list("/b.txt").unwrap_err()
+        );
+
+        let paths = |items: Vec<Item>| items.into_iter().map(|i| i.path).collect::<Vec<_>>();
+        assert_eq!(vec!["/a/h.lst", "/d/d.log"], paths(fs.find_glob("*.l*")));
+        assert_eq!(vec!["/d/d.log", "/d/d.ext"], paths(fs.find_glob("/d/d.*")));
+        assert_eq!(vec!["/c.dat"], paths(fs.find_ext(".dat")));
+        assert_eq!(
+            vec!["/a", "/a/e"],
+            paths(fs.dirs_where(|size| size <= 100_000))
+        );
+        assert_eq!(
+            95_437,
+            fs.dirs_where(|size| size <= 100_000)
+                .iter()
+                .map(|d| d.size)
+                .sum::<i64>()
+        );
+    }
+
+    #[test]
+    fn query_filters() {
+        let fs = FS::new(test_data()).unwrap();
+        let find = |expr: &str| -> Vec<String> {
+            fs.find(&Filter::parse(expr).unwrap())
+                .into_iter()
+                .map(|i| i.path)
+                .collect()
+        };
+
+        assert_eq!(vec!["/b.txt", "/c.dat"], find("file size>8M"));
+        assert_eq!(
+            vec!["/a/e/i", "/a/f", "/a/g", "/d/j", "/d/k"],
+            find("file not name=*.*")
+        );
+        assert_eq!(vec!["/a/e", "/a/e/i"], find("under=/a/e or size=584"));
+        assert_eq!(vec!["/a/e", "/a/e/i"], find("size<=584"));
+        assert_eq!(
+            vec!["/", "/a", "/a/e", "/a/f", "/a/g", "/d"],
+            find("(dir and !under=/a) or path=/a/?")
+        );
+        assert_eq!(vec!["/a/h.lst", "/d/d.log"], find("ext=lst or ext=log"));
+        assert_eq!(
+            vec!["/a/e/i", "/a/f", "/a/g"],
+            find("under=/a/ file size<30k")
+        );
+
+        for (expr, err) in [
+            ("", FilterError::Empty),
+            ("()", FilterError::Empty),
+            ("dir or", FilterError::Dangling("or")),
+            ("not", FilterError::Dangling("not")),
+            ("(dir", FilterError::Unbalanced),
+            ("dir)", FilterError::Unbalanced),
+            ("big", FilterError::UnknownTerm("big".to_string())),
+            ("size~4", FilterError::UnknownTerm("size~4".to_string())),
+            ("size>4X", FilterError::BadSize("4X".to_string())),
+        ] {
+            assert_eq!(Err(err), Filter::parse(expr), "{expr}");
+        }
+    }
+
+    #[test]
+    fn globs() {
+        for (pattern, text, want) in [
+            ("*", "", true),
+            ("*.txt", "b.txt", true),
+            ("*.txt", "b.txt.gz", false),
+            ("?.*", "b.txt", true),
+            ("?", "ab", false),
+            ("a*b*c", "aXbYbZc", true),
+            ("a*b*c", "aXbYbZ", false),
+            ("/a/*", "/a/e/i", true),
+        ] {
+            assert_eq!(want, glob(pattern, text), "{pattern} {text}");
+        }
+    }
+}
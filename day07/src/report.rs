@@ -0,0 +1,362 @@
+use id_tree::NodeId;
+use std::fmt::Write;
+
+use crate::{Entry, FS};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum Style {
+    /// Indented like `tree`, with files and directories in listing order under each parent
+    #[default]
+    Tree,
+    /// One `size path` line per directory like `du`, children before their parents
+    Du,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum SortBy {
+    /// The order things were found in the transcript
+    #[default]
+    Listed,
+    /// Biggest first
+    Size,
+    Name,
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum TopKind {
+    Dirs,
+    Files,
+}
+
+/// How to lay out a report.  Directories that weren't fully listed only have a lower bound for
+/// their size, which is shown with a trailing `+`
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct ReportOpts {
+    pub style: Style,
+    /// How many levels below the root to show
+    pub max_depth: Option<usize>,
+    pub sort: SortBy,
+    pub human: bool,
+    /// Show full paths instead of names in tree mode (du mode always uses paths)
+    pub full_paths: bool,
+    pub percent: bool,
+    /// Show files in du mode (tree mode always shows them)
+    pub all: bool,
+}
+
+impl ReportOpts {
+    /// Formats a size the way these options ask for
+    pub fn size(&self, size: i64) -> String {
+        if self.human {
+            human_size(size)
+        } else {
+            size.to_string()
+        }
+    }
+}
+
+/// Formats a size the way `du -h` does, in powers of 1024
+pub fn human_size(size: i64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+
+    if size < 1024 {
+        return size.to_string();
+    }
+
+    // Round first so anything that rounds up to the next unit is shown in it
+    let mut val = size as f64 / 1024.0;
+    let mut unit = 0;
+    loop {
+        let tenths = (val * 10.0).round() / 10.0;
+        let rounded = if tenths < 10.0 { tenths } else { val.round() };
+
+        if rounded >= 1024.0 && unit + 1 < UNITS.len() {
+            val /= 1024.0;
+            unit += 1;
+        } else if rounded < 10.0 {
+            return format!("{rounded:.1}{}", UNITS[unit]);
+        } else {
+            return format!("{rounded:.0}{}", UNITS[unit]);
+        }
+    }
+}
+
+impl FS {
+    pub fn report(&self, opts: &ReportOpts) -> String {
+        let mut out = String::with_capacity(4096);
+        let root_id = self.root_id();
+
+        match opts.style {
+            Style::Tree => {
+                writeln!(out, "{}", self.describe(&root_id, None, opts)).unwrap();
+                self.report_tree(&mut out, &root_id, "", 1, opts);
+            }
+            Style::Du => self.report_du(&mut out, &root_id, None, 0, opts),
+        }
+
+        out
+    }
+
+    fn report_tree(
+        &self,
+        out: &mut String,
+        id: &NodeId,
+        prefix: &str,
+        depth: usize,
+        opts: &ReportOpts,
+    ) {
+        if opts.max_depth.is_some_and(|max| depth > max) {
+            return;
+        }
+
+        let parent_size = self.tree.get(id).unwrap().data().size();
+        let children = self.sorted_children(id, opts.sort);
+
+        for (idx, child) in children.iter().enumerate() {
+            let last = idx + 1 == children.len();
+            let (branch, indent) = if last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+
+            writeln!(
+                out,
+                "{prefix}{branch}{}",
+                self.describe(child, Some(parent_size), opts)
+            )
+            .unwrap();
+            self.report_tree(out, child, &format!("{prefix}{indent}"), depth + 1, opts);
+        }
+    }
+
+    fn report_du(
+        &self,
+        out: &mut String,
+        id: &NodeId,
+        parent_size: Option<i64>,
+        depth: usize,
+        opts: &ReportOpts,
+    ) {
+        let entry = self.tree.get(id).unwrap().data();
+        let is_dir = matches!(entry, Entry::Dir(_));
+
+        if is_dir && opts.max_depth.is_none_or(|max| depth < max) {
+            for child in self.sorted_children(id, opts.sort) {
+                let child_is_dir = matches!(self.tree.get(&child).unwrap().data(), Entry::Dir(_));
+                if child_is_dir || opts.all {
+                    self.report_du(out, &child, Some(entry.size()), depth + 1, opts);
+                }
+            }
+        }
+
+        write!(out, "{}\t", self.size_of(id, opts)).unwrap();
+        if opts.percent {
+            write!(out, "{}\t", percent(entry.size(), parent_size)).unwrap();
+        }
+        writeln!(out, "{}", self.path(id)).unwrap();
+    }
+
+    /// A single tree line, like `a (94853, 0.2%)`
+    fn describe(&self, id: &NodeId, parent_size: Option<i64>, opts: &ReportOpts) -> String {
+        let entry = self.tree.get(id).unwrap().data();
+
+        let mut desc = if opts.full_paths {
+            self.path(id)
+        } else {
+            entry.name().to_string()
+        };
+        if let Entry::Dir(_) = entry {
+            if !desc.ends_with('/') {
+                desc.push('/');
+            }
+        }
+
+        write!(desc, " ({}", self.size_of(id, opts)).unwrap();
+        if opts.percent {
+            write!(desc, ", {}", percent(entry.size(), parent_size)).unwrap();
+        }
+        desc.push(')');
+
+        desc
+    }
+
+    fn size_of(&self, id: &NodeId, opts: &ReportOpts) -> String {
+        let entry = self.tree.get(id).unwrap().data();
+        let mut size = opts.size(entry.size());
+
+        if let Entry::Dir(_) = entry {
+            if !self.size_known(id) {
+                size.push('+');
+            }
+        }
+
+        size
+    }
+
+    fn sorted_children(&self, id: &NodeId, sort: SortBy) -> Vec<NodeId> {
+        let mut children: Vec<NodeId> = self.tree.children_ids(id).unwrap().cloned().collect();
+        let data = |id: &NodeId| self.tree.get(id).unwrap().data();
+
+        match sort {
+            SortBy::Listed => (),
+            SortBy::Size => children.sort_by_key(|id| std::cmp::Reverse(data(id).size())),
+            SortBy::Name => children.sort_by(|a, b| data(a).name().cmp(data(b).name())),
+        }
+
+        children
+    }
+
+    /// The `n` biggest directories (not counting `/`) or files, biggest first
+    pub fn top(&self, n: usize, kind: TopKind) -> Vec<(String, i64)> {
+        let root_id = self.root_id();
+
+        let mut found: Vec<(String, i64)> = self
+            .tree
+            .traverse_pre_order_ids(&root_id)
+            .unwrap()
+            .filter(|id| *id != root_id)
+            .filter_map(|id| {
+                let entry = self.tree.get(&id).unwrap().data();
+                match (entry, kind) {
+                    (Entry::Dir(_), TopKind::Dirs) | (Entry::File(_), TopKind::Files) => {
+                        Some((self.path(&id), entry.size()))
+                    }
+                    _ => None,
+                }
+            })
+            .collect();
+
+        found.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        found.truncate(n);
+        found
+    }
+}
+
+fn percent(size: i64, parent_size: Option<i64>) -> String {
+    match parent_size {
+        Some(0) => "0.0%".to_string(),
+        Some(parent) => format!("{:.1}%", size as f64 * 100.0 / parent as f64),
+        None => "100.0%".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_data;
+
+    #[test]
+    fn report_tree() {
+        let fs = FS::new(test_data()).unwrap();
+
+        let want = "/ (48381165)
+├── a/ (94853)
+│   ├── e/ (584)
+│   │   └── i (584)
+│   ├── f (29116)
+│   ├── g (2557)
+│   └── h.lst (62596)
+├── b.txt (14848514)
+├── c.dat (8504156)
+└── d/ (24933642)
+    ├── j (4060174)
+    ├── d.log (8033020)
+    ├── d.ext (5626152)
+    └── k (7214296)
+";
+        assert_eq!(want, fs.report(&ReportOpts::default()));
+
+        let opts = ReportOpts {
+            max_depth: Some(1),
+            sort: SortBy::Name,
+            full_paths: true,
+            percent: true,
+            human: true,
+            ..Default::default()
+        };
+        let want = "/ (46M, 100.0%)
+├── /a/ (93K, 0.2%)
+├── /b.txt (14M, 30.7%)
+├── /c.dat (8.1M, 17.6%)
+└── /d/ (24M, 51.5%)
+";
+        assert_eq!(want, fs.report(&opts));
+    }
+
+    #[test]
+    fn report_du() {
+        let fs = FS::new(test_data()).unwrap();
+
+        let opts = ReportOpts {
+            style: Style::Du,
+            ..Default::default()
+        };
+        assert_eq!(
+            "584\t/a/e\n94853\t/a\n24933642\t/d\n48381165\t/\n",
+            fs.report(&opts)
+        );
+
+        let opts = ReportOpts {
+            style: Style::Du,
+            max_depth: Some(1),
+            sort: SortBy::Size,
+            all: true,
+            human: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            "24M\t/d\n14M\t/b.txt\n8.1M\t/c.dat\n93K\t/a\n46M\t/\n",
+            fs.report(&opts)
+        );
+
+        // The listing of /a never happened so its size is only a lower bound
+        let fs = FS::new("$ ls\ndir a\n10 b\n$ cd a\n$ mkdir c\n$ touch c/d 5").unwrap();
+        assert_eq!("5\t/a/c\n5+\t/a\n15+\t/\n", fs.report(&opts_du()));
+    }
+
+    fn opts_du() -> ReportOpts {
+        ReportOpts {
+            style: Style::Du,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn report_top() {
+        let fs = FS::new(test_data()).unwrap();
+        assert_eq!(
+            vec![
+                ("/b.txt".to_string(), 14848514),
+                ("/c.dat".to_string(), 8504156)
+            ],
+            fs.top(2, TopKind::Files)
+        );
+        assert_eq!(
+            vec![
+                ("/d".to_string(), 24933642),
+                ("/a".to_string(), 94853),
+                ("/a/e".to_string(), 584)
+            ],
+            fs.top(5, TopKind::Dirs)
+        );
+    }
+
+    #[test]
+    fn human_sizes() {
+        for (size, want) in [
+            (0, "0"),
+            (1023, "1023"),
+            (1024, "1.0K"),
+            (1536, "1.5K"),
+            (10 * 1024, "10K"),
+            (10 * 1024 - 1, "10K"),
+            (1024 * 1024 - 1, "1.0M"),
+            (1024 * 1024 - 512, "1.0M"),
+            (1024 * 1024 - 600, "1023K"),
+            (5 * 1024 * 1024 * 1024, "5.0G"),
+        ] {
+            assert_eq!(want, human_size(size));
+        }
+    }
+}
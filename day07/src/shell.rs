@@ -0,0 +1,706 @@
+use id_tree::{InsertBehavior, MoveBehavior, Node, NodeId, RemoveBehavior};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use crate::{Entry, FsEntry, FS};
+
+/// Why a line of the transcript couldn't be replayed
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum ErrorKind {
+    UnknownCommand(String),
+    MissingArg(&'static str),
+    BadEntry,
+    BadName(String),
+    BadSize(String),
+    AboveRoot,
+    NotFound(String),
+    NotADir(String),
+    IsADir(String),
+    AlreadyExists(String),
+    /// Removing or moving a directory we're inside of (or moving one into itself)
+    Busy(String),
+    UnexpectedOutput,
+    PwdMismatch {
+        want: String,
+    },
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::UnknownCommand(cmd) => write!(f, "unknown command `{cmd}`"),
+            ErrorKind::MissingArg(arg) => write!(f, "missing {arg}"),
+            ErrorKind::BadEntry => write!(f, "not a valid ls entry"),
+            ErrorKind::BadName(name) => write!(f, "`{name}` isn't a valid name"),
+            ErrorKind::BadSize(size) => write!(f, "`{size}` isn't a valid size"),
+            ErrorKind::AboveRoot => write!(f, "can't go above /"),
+            ErrorKind::NotFound(path) => write!(f, "{path}: no such file or directory"),
+            ErrorKind::NotADir(path) => write!(f, "{path}: not a directory"),
+            ErrorKind::IsADir(path) => write!(f, "{path}: is a directory"),
+            ErrorKind::AlreadyExists(path) => write!(f, "{path}: already exists"),
+            ErrorKind::Busy(path) => write!(f, "{path}: directory is in use"),
+            ErrorKind::UnexpectedOutput => write!(f, "output without a command that prints it"),
+            ErrorKind::PwdMismatch { want } => write!(f, "expected pwd to print {want}"),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ShellError {
+    pub line_no: usize,
+    pub line: String,
+    pub kind: ErrorKind,
+}
+
+impl Display for ShellError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {} `{}`: {}", self.line_no, self.line, self.kind)
+    }
+}
+
+impl std::error::Error for ShellError {}
+
+/// How a listing disagreed with what we already knew about a directory.  The latest listing
+/// always wins
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// A file was listed again with a different size
+    Resized { old: i64, new: i64 },
+    /// Listed as a file where there used to be a directory, or the other way round
+    KindChanged,
+    /// The same name showed up twice in one listing (only the first is kept)
+    Duplicate,
+    /// Was there before but missing from a later listing
+    Disappeared,
+    /// Missing from an earlier listing of the same directory
+    Appeared,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct Conflict {
+    pub line_no: usize,
+    pub path: String,
+    pub kind: ConflictKind,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}: ", self.line_no, self.path)?;
+
+        match self.kind {
+            ConflictKind::Resized { old, new } => write!(f, "resized from {old} to {new}"),
+            ConflictKind::KindChanged => write!(f, "changed between a file and a directory"),
+            ConflictKind::Duplicate => write!(f, "listed twice"),
+            ConflictKind::Disappeared => write!(f, "missing from a later listing"),
+            ConflictKind::Appeared => write!(f, "missing from an earlier listing"),
+        }
+    }
+}
+
+/// What the lines that aren't commands belong to
+#[derive(Eq, PartialEq, Clone, Debug)]
+enum Output {
+    None,
+    Ls(Listing),
+    Pwd,
+}
+
+/// An `ls` that is being read in
+#[derive(Eq, PartialEq, Clone, Debug)]
+struct Listing {
+    line_no: usize,
+    relisting: bool,
+    seen: HashSet<String>,
+}
+
+impl FS {
+    /// Replays a shell transcript against the tree.  Supports `cd`, `ls`, `pwd`, `mkdir`,
+    /// `rm [-r]`, `mv` and `touch <path> [size]`, with absolute or relative paths
+    pub(crate) fn parse_cmds(&mut self, lines: &str) -> Result<(), ShellError> {
+        let mut cur_id = self.root_id();
+        let mut output = Output::None;
+
+        for (idx, line) in lines.lines().enumerate() {
+            let line_no = idx + 1;
+
+            let result = if let Some(cmd) = line.strip_prefix("$ ") {
+                if let Output::Ls(listing) = std::mem::replace(&mut output, Output::None) {
+                    self.finish_listing(&cur_id, listing);
+                }
+
+                self.run_cmd(&mut cur_id, cmd, line_no)
+                    .map(|out| output = out)
+            } else {
+                match &mut output {
+                    Output::Ls(listing) => self.add_entry(&cur_id, line, line_no, listing),
+                    Output::Pwd => {
+                        output = Output::None;
+                        let want = self.path(&cur_id);
+                        if line == want {
+                            Ok(())
+                        } else {
+                            Err(ErrorKind::PwdMismatch { want })
+                        }
+                    }
+                    Output::None => Err(ErrorKind::UnexpectedOutput),
+                }
+            };
+
+            result.map_err(|kind| ShellError {
+                line_no,
+                line: line.to_string(),
+                kind,
+            })?;
+        }
+
+        if let Output::Ls(listing) = output {
+            self.finish_listing(&cur_id, listing);
+        }
+
+        Ok(())
+    }
+
+    fn run_cmd(
+        &mut self,
+        cur_id: &mut NodeId,
+        cmd: &str,
+        line_no: usize,
+    ) -> Result<Output, ErrorKind> {
+        let mut args = cmd.split_whitespace();
+        let name = args.next().unwrap_or_default();
+        let mut arg = |what| args.next().ok_or(ErrorKind::MissingArg(what));
+
+        match name {
+            "cd" => {
+                let path = arg("directory")?;
+                let id = self.resolve(cur_id, path)?;
+                self.dir(&id)?;
+                *cur_id = id;
+            }
+
+            "ls" => {
+                return Ok(Output::Ls(Listing {
+                    line_no,
+                    relisting: !self.listed.insert(cur_id.clone()),
+                    seen: HashSet::new(),
+                }))
+            }
+            "pwd" => return Ok(Output::Pwd),
+
+            "mkdir" => {
+                let path = arg("directory")?;
+                let (parent, name) = self.resolve_parent(cur_id, path)?;
+                if self.child(&parent, name).is_some() {
+                    return Err(ErrorKind::AlreadyExists(self.join(&parent, name)));
+                }
+                // We just made it so we know exactly what's in it
+                let id = self.insert(&parent, Entry::new_dir(name));
+                self.listed.insert(id);
+            }
+
+            "touch" => {
+                let path = arg("file")?;
+                let size = args
+                    .next()
+                    .map(|s| match s.parse::<i64>() {
+                        Ok(size) if size >= 0 => Ok(size),
+                        _ => Err(ErrorKind::BadSize(s.to_string())),
+                    })
+                    .transpose()?;
+                self.touch(cur_id, path, size)?;
+            }
+
+            "rm" => {
+                let mut path = arg("file")?;
+                let recursive = matches!(path, "-r" | "-rf" | "-fr");
+                if recursive {
+                    path = arg("file")?;
+                }
+                self.rm(cur_id, path, recursive)?;
+            }
+
+            "mv" => {
+                let src = arg("source")?;
+                let dst = arg("destination")?;
+                self.mv(cur_id, src, dst)?;
+            }
+
+            _ => return Err(ErrorKind::UnknownCommand(cmd.to_string())),
+        }
+
+        Ok(Output::None)
+    }
+
+    /// Adds a line of `ls` output to the current directory, merging it with anything that's
+    /// already known to be there
+    fn add_entry(
+        &mut self,
+        cur_id: &NodeId,
+        line: &str,
+        line_no: usize,
+        listing: &mut Listing,
+    ) -> Result<(), ErrorKind> {
+        let entry = match line.strip_prefix("dir ") {
+            Some(name) => Entry::new_dir(FS::check_name(name)?),
+
+            None => {
+                let (num, idx) = atoi::FromRadix10::from_radix_10(line.as_bytes());
+                if idx == 0 || line.as_bytes().get(idx) != Some(&b' ') {
+                    return Err(ErrorKind::BadEntry);
+                }
+
+                Entry::new_file(FS::check_name(&line[idx + 1..])?, num)
+            }
+        };
+
+        let name = entry.name();
+        let conflict = |fs: &mut FS, kind| {
+            fs.conflicts.push(Conflict {
+                line_no,
+                path: fs.join(cur_id, name),
+                kind,
+            })
+        };
+
+        if !listing.seen.insert(name.to_string()) {
+            conflict(self, ConflictKind::Duplicate);
+            return Ok(());
+        }
+
+        let id = match self.child(cur_id, name) {
+            Some(id) => id,
+            None => {
+                if listing.relisting {
+                    conflict(self, ConflictKind::Appeared);
+                }
+                self.insert(cur_id, entry);
+                return Ok(());
+            }
+        };
+
+        match (self.tree.get_mut(&id).unwrap().data_mut(), &entry) {
+            (Entry::Dir(_), Entry::Dir(_)) => (),
+
+            (Entry::File(old), Entry::File(new)) => {
+                if old.size != new.size {
+                    let kind = ConflictKind::Resized {
+                        old: old.size,
+                        new: new.size,
+                    };
+                    old.size = new.size;
+                    conflict(self, kind);
+                }
+            }
+
+            _ => {
+                conflict(self, ConflictKind::KindChanged);
+                self.remove(id);
+                self.insert(cur_id, entry.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops anything the listing didn't mention
+    fn finish_listing(&mut self, dir: &NodeId, listing: Listing) {
+        let missing: Vec<NodeId> = self
+            .tree
+            .children_ids(dir)
+            .unwrap()
+            .filter(|&id| {
+                let name = self.tree.get(id).unwrap().data().name();
+                !listing.seen.contains(name)
+            })
+            .cloned()
+            .collect();
+
+        for id in missing {
+            self.conflicts.push(Conflict {
+                line_no: listing.line_no,
+                path: self.path(&id),
+                kind: ConflictKind::Disappeared,
+            });
+            self.remove(id);
+        }
+    }
+
+    fn touch(&mut self, cur_id: &NodeId, path: &str, size: Option<i64>) -> Result<(), ErrorKind> {
+        match self.resolve(cur_id, path) {
+            Ok(id) => match self.tree.get_mut(&id).unwrap().data_mut() {
+                Entry::File(fe) => {
+                    if let Some(size) = size {
+                        fe.size = size;
+                    }
+                    Ok(())
+                }
+                Entry::Dir(_) if size.is_some() => Err(ErrorKind::IsADir(self.path(&id))),
+                Entry::Dir(_) => Ok(()),
+            },
+
+            Err(ErrorKind::NotFound(_)) => {
+                let (parent, name) = self.resolve_parent(cur_id, path)?;
+                self.insert(&parent, Entry::new_file(name, size.unwrap_or_default()));
+                Ok(())
+            }
+
+            Err(e) => Err(e),
+        }
+    }
+
+    fn rm(&mut self, cur_id: &NodeId, path: &str, recursive: bool) -> Result<(), ErrorKind> {
+        let id = self.resolve(cur_id, path)?;
+
+        if let Entry::Dir(_) = self.tree.get(&id).unwrap().data() {
+            if !recursive {
+                return Err(ErrorKind::IsADir(self.path(&id)));
+            }
+            if self.is_within(cur_id, &id) {
+                return Err(ErrorKind::Busy(self.path(&id)));
+            }
+        }
+
+        self.remove(id);
+        Ok(())
+    }
+
+    fn mv(&mut self, cur_id: &NodeId, src: &str, dst: &str) -> Result<(), ErrorKind> {
+        let src_id = self.resolve(cur_id, src)?;
+        let name = self.tree.get(&src_id).unwrap().data().name().to_string();
+
+        // Moving onto an existing directory puts the source inside it
+        let (parent, name) = match self.resolve(cur_id, dst) {
+            Ok(id) => {
+                self.dir(&id)
+                    .map_err(|_| ErrorKind::AlreadyExists(self.path(&id)))?;
+                (id, name)
+            }
+            Err(ErrorKind::NotFound(_)) => {
+                let (parent, name) = self.resolve_parent(cur_id, dst)?;
+                (parent, name.to_string())
+            }
+            Err(e) => return Err(e),
+        };
+
+        if self.is_within(&parent, &src_id) {
+            return Err(ErrorKind::Busy(self.path(&src_id)));
+        }
+        if let Some(id) = self.child(&parent, &name) {
+            return Err(ErrorKind::AlreadyExists(self.path(&id)));
+        }
+
+        self.tree
+            .move_node(&src_id, MoveBehavior::ToParent(&parent))
+            .unwrap();
+        self.tree.get_mut(&src_id).unwrap().data_mut().rename(name);
+
+        Ok(())
+    }
+
+    /// Finds the node `path` points at, starting from `cur_id` unless it's absolute
+    pub fn resolve(&self, cur_id: &NodeId, path: &str) -> Result<NodeId, ErrorKind> {
+        let mut id = if path.starts_with('/') {
+            self.root_id()
+        } else {
+            cur_id.clone()
+        };
+
+        for seg in path.split('/').filter(|s| !s.is_empty()) {
+            id = match seg {
+                "." => id,
+                ".." => self
+                    .tree
+                    .get(&id)
+                    .unwrap()
+                    .parent()
+                    .cloned()
+                    .ok_or(ErrorKind::AboveRoot)?,
+                name => {
+                    self.dir(&id)?;
+                    self.child(&id, name)
+                        .ok_or_else(|| ErrorKind::NotFound(self.join(&id, name)))?
+                }
+            };
+        }
+
+        Ok(id)
+    }
+
+    /// Splits `path` into the directory it's in (which has to exist) and its name
+    fn resolve_parent<'a>(
+        &self,
+        cur_id: &NodeId,
+        path: &'a str,
+    ) -> Result<(NodeId, &'a str), ErrorKind> {
+        let path = path.trim_end_matches('/');
+        let (parent, name) = match path.rsplit_once('/') {
+            Some(("", name)) => (self.root_id(), name),
+            Some((dir, name)) => (self.resolve(cur_id, dir)?, name),
+            None => (cur_id.clone(), path),
+        };
+
+        self.dir(&parent)?;
+        Ok((parent, FS::check_name(name)?))
+    }
+
+    pub(crate) fn check_name(name: &str) -> Result<&str, ErrorKind> {
+        match name {
+            "" | "." | ".." => Err(ErrorKind::BadName(name.to_string())),
+            _ if name.contains('/') => Err(ErrorKind::BadName(name.to_string())),
+            _ => Ok(name),
+        }
+    }
+
+    /// Makes sure `id` is a directory
+    pub(crate) fn dir(&self, id: &NodeId) -> Result<(), ErrorKind> {
+        match self.tree.get(id).unwrap().data() {
+            Entry::Dir(_) => Ok(()),
+            Entry::File(_) => Err(ErrorKind::NotADir(self.path(id))),
+        }
+    }
+
+    pub(crate) fn child(&self, id: &NodeId, name: &str) -> Option<NodeId> {
+        self.tree
+            .children_ids(id)
+            .unwrap()
+            .find(|&child| self.tree.get(child).unwrap().data().name() == name)
+            .cloned()
+    }
+
+    /// Removes a node and everything under it
+    fn remove(&mut self, id: NodeId) {
+        for child in self.tree.traverse_pre_order_ids(&id).unwrap() {
+            self.listed.remove(&child);
+        }
+
+        self.tree
+            .remove_node(id, RemoveBehavior::DropChildren)
+            .unwrap();
+    }
+
+    pub(crate) fn insert(&mut self, parent: &NodeId, entry: Entry) -> NodeId {
+        self.tree
+            .insert(Node::new(entry), InsertBehavior::UnderNode(parent))
+            .unwrap()
+    }
+
+    /// True if `id` is `dir` or somewhere underneath it
+    fn is_within(&self, id: &NodeId, dir: &NodeId) -> bool {
+        id == dir || self.tree.ancestor_ids(id).unwrap().any(|a| a == dir)
+    }
+
+    /// The full path of a node, like `/a/e/i`
+    pub fn path(&self, id: &NodeId) -> String {
+        let mut names: Vec<&str> = self
+            .tree
+            .ancestor_ids(id)
+            .unwrap()
+            .map(|a| self.tree.get(a).unwrap().data().name())
+            .collect();
+
+        // Drop the root's name since that's the leading slash
+        names.pop();
+        if names.is_empty() && self.tree.root_node_id() == Some(id) {
+            return "/".to_string();
+        }
+
+        names.reverse();
+        names.push(self.tree.get(id).unwrap().data().name());
+
+        let mut path = String::with_capacity(64);
+        for name in names {
+            path.push('/');
+            path.push_str(name);
+        }
+        path
+    }
+
+    pub(crate) fn join(&self, dir: &NodeId, name: &str) -> String {
+        let mut path = self.path(dir);
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push_str(name);
+        path
+    }
+}
+
+impl Entry {
+    pub(crate) fn new_dir(name: &str) -> Self {
+        Entry::Dir(FsEntry {
+            name: name.to_string(),
+            size: 0,
+        })
+    }
+
+    pub(crate) fn new_file(name: &str, size: i64) -> Self {
+        Entry::File(FsEntry {
+            name: name.to_string(),
+            size,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Entry::Dir(d) => &d.name,
+            Entry::File(d) => &d.name,
+        }
+    }
+
+    fn rename(&mut self, name: String) {
+        match self {
+            Entry::Dir(d) => d.name = name,
+            Entry::File(d) => d.name = name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_data;
+
+    #[test]
+    fn shell_commands() {
+        let fs = FS::new(
+            "$ cd /
+$ mkdir a
+$ mkdir /a/b
+$ cd a/b
+$ pwd
+/a/b
+$ touch c.txt 100
+$ touch ../d 50
+$ cd ../..
+$ ls
+dir a
+dir e
+20 f
+$ cd /e
+$ touch g 7
+$ cd ..
+$ mv f a/b
+$ mv a/d e/h
+$ mv e a
+$ touch a/e/g 9
+$ rm -r a/b
+$ pwd
+/",
+        )
+        .unwrap();
+
+        let root = fs.root_id();
+        assert_eq!(59, fs.tree.get(&root).unwrap().data().size());
+
+        let h = fs.resolve(&root, "/a/e/h").unwrap();
+        assert_eq!("/a/e/h", fs.path(&h));
+        assert_eq!(50, fs.tree.get(&h).unwrap().data().size());
+        assert!(fs.resolve(&root, "a/b").is_err());
+    }
+
+    #[test]
+    fn shell_errors() {
+        for (transcript, line_no, kind) in [
+            ("$ cd /\n$ cd ..", 2, ErrorKind::AboveRoot),
+            ("$ cd x", 1, ErrorKind::NotFound("/x".to_string())),
+            (
+                "$ touch f 1\n$ cd f",
+                2,
+                ErrorKind::NotADir("/f".to_string()),
+            ),
+            ("$ mkdir a\n$ cd a/../..", 2, ErrorKind::AboveRoot),
+            (
+                "$ mkdir a\n$ mkdir a",
+                2,
+                ErrorKind::AlreadyExists("/a".to_string()),
+            ),
+            ("$ mkdir a\n$ rm a", 2, ErrorKind::IsADir("/a".to_string())),
+            (
+                "$ mkdir a\n$ cd a\n$ rm -r /a",
+                3,
+                ErrorKind::Busy("/a".to_string()),
+            ),
+            (
+                "$ mkdir a\n$ mv a a/b",
+                2,
+                ErrorKind::Busy("/a".to_string()),
+            ),
+            (
+                "$ pwd\n/x",
+                2,
+                ErrorKind::PwdMismatch {
+                    want: "/".to_string(),
+                },
+            ),
+            ("$ cd /\n12 f", 2, ErrorKind::UnexpectedOutput),
+            ("$ ls\nabc", 2, ErrorKind::BadEntry),
+            ("$ touch a x", 1, ErrorKind::BadSize("x".to_string())),
+            ("$ touch a -5", 1, ErrorKind::BadSize("-5".to_string())),
+            ("$ cat a", 1, ErrorKind::UnknownCommand("cat a".to_string())),
+        ] {
+            let err = FS::new(transcript).unwrap_err();
+            assert_eq!((line_no, kind), (err.line_no, err.kind), "{transcript}");
+        }
+    }
+
+    #[test]
+    fn relisting() {
+        let same = format!(
+            "{}\n$ cd /\n$ ls\ndir a\n14848514 b.txt\n8504156 c.dat\ndir d",
+            test_data()
+        );
+        let fs = FS::new(&same).unwrap();
+        assert!(fs.conflicts.is_empty());
+        assert_eq!(95_437, fs.sum_dirs_under(100_000));
+        assert_eq!(FS::new(test_data()).unwrap().tree, fs.tree);
+
+        let changed = format!(
+            "{}\n$ cd /a\n$ ls\n29116 f\n3000 g\n3000 g\n1 h.lst\ndir h.lst\ndir x\n$ cd x",
+            test_data()
+        );
+        let fs = FS::new(&changed).unwrap();
+
+        let conflicts: Vec<(usize, &str, ConflictKind)> = fs
+            .conflicts
+            .iter()
+            .map(|c| (c.line_no, c.path.as_str(), c.kind.clone()))
+            .collect();
+        assert_eq!(
+            vec![
+                (
+                    27,
+                    "/a/g",
+                    ConflictKind::Resized {
+                        old: 2557,
+                        new: 3000
+                    }
+                ),
+                (28, "/a/g", ConflictKind::Duplicate),
+                (29, "/a/h.lst", ConflictKind::Resized { old: 62596, new: 1 }),
+                (30, "/a/h.lst", ConflictKind::Duplicate),
+                (31, "/a/x", ConflictKind::Appeared),
+                (25, "/a/e", ConflictKind::Disappeared),
+            ],
+            conflicts
+        );
+
+        let root = fs.root_id();
+        assert_eq!(
+            32_117,
+            fs.tree
+                .get(&fs.resolve(&root, "a").unwrap())
+                .unwrap()
+                .data()
+                .size()
+        );
+        assert!(fs.size_known(&fs.resolve(&root, "d").unwrap()));
+        assert!(!fs.size_known(&fs.resolve(&root, "a").unwrap()));
+        assert!(!fs.size_known(&root));
+
+        let fs = FS::new("$ ls\n12 a\n$ ls\ndir a").unwrap();
+        assert_eq!(ConflictKind::KindChanged, fs.conflicts[0].kind);
+        assert!(!fs.size_known(&fs.root_id()));
+    }
+}
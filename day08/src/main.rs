@@ -1,30 +1,229 @@
+mod render;
+mod sight;
+
+use crate::render::Layer;
+use crate::sight::Observer;
 use ndarray::{Array, ArrayBase, OwnedRepr};
+use rayon::prelude::*;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
 
 const PART_1: bool = false;
+/// Print how many trees can be seen from each number of sides and the best tree with a clear
+/// view each way
+const BREAKDOWN: bool = false;
+/// Write PPM and PNG heatmaps of every layer into this directory
+const HEATMAPS: Option<&str> = None;
+/// How many pixels across each tree is in the heatmaps
+const HEATMAP_SCALE: usize = 4;
+/// Print the forest to the terminal coloured by a layer
+const ANSI: Option<Layer> = None;
+/// Put a lookout tower this tall on the tree at `(row, col)` and print what can be seen from it
+const LOOKOUT: Option<((usize, usize), f64)> = None;
 
 static PART_1_DATA: &str = include_str!("input");
 
-fn main() {
+fn main() -> Result<(), Box<dyn Error>> {
+    if BREAKDOWN {
+        breakdown(&Forest::new(PART_1_DATA)?);
+    }
+    if let Some(dir) = HEATMAPS {
+        heatmaps(&Forest::new(PART_1_DATA)?, Path::new(dir))?;
+    }
+    if let Some(layer) = ANSI {
+        print!("{}", Forest::new(PART_1_DATA)?.ansi(layer));
+    }
+    if let Some((pos, tower)) = LOOKOUT {
+        lookout(&Forest::new(PART_1_DATA)?, pos, tower);
+    }
+
     if PART_1 {
-        part1();
+        part1()?;
     } else {
-        part2();
+        part2()?;
+    }
+
+    Ok(())
+}
+
+fn part1() -> Result<(), ParseError> {
+    println!("{}", Forest::new(PART_1_DATA)?.count_vis());
+    Ok(())
+}
+
+fn part2() -> Result<(), ParseError> {
+    println!("{}", Forest::new(PART_1_DATA)?.scenic_score());
+    Ok(())
+}
+
+fn heatmaps(forest: &Forest, dir: &Path) -> Result<(), Box<dyn Error>> {
+    for (name, layer) in [
+        ("heights", Layer::Height),
+        ("visible", Layer::Visible),
+        ("scenic", Layer::Scenic),
+    ] {
+        let img = forest.render(layer, HEATMAP_SCALE);
+        img.write_ppm(&mut BufWriter::new(File::create(
+            dir.join(format!("{name}.ppm")),
+        )?))?;
+        img.write_png(BufWriter::new(File::create(
+            dir.join(format!("{name}.png")),
+        )?))?;
+    }
+
+    Ok(())
+}
+
+fn lookout(forest: &Forest, pos: (usize, usize), tower: f64) {
+    let obs = Observer::on_tree(forest, pos, tower);
+    let seen = forest.seen_by(&obs);
+    println!(
+        "a {tower} tall tower at {pos:?} can see {} of {} trees",
+        seen.len(),
+        forest.trees.len()
+    );
+
+    for (name, dir) in [
+        ("north", (-1.0, 0.0)),
+        ("north east", (-1.0, 1.0)),
+        ("east", (0.0, 1.0)),
+        ("south east", (1.0, 1.0)),
+        ("south", (1.0, 0.0)),
+        ("south west", (1.0, -1.0)),
+        ("west", (0.0, -1.0)),
+        ("north west", (-1.0, -1.0)),
+    ] {
+        println!("  {name}: {}", forest.look(&obs, dir).len());
+    }
+}
+
+fn breakdown(forest: &Forest) {
+    for sides in 0..=4 {
+        let count = forest.visible_from_sides(sides).count();
+        println!("visible from {sides} sides: {count}");
+    }
+
+    for dir in Dir::ALL {
+        if let Some(((row, col), tree)) = forest.best_where(|t| t.visible_from(dir)) {
+            let dists: Vec<String> = Dir::ALL
+                .iter()
+                .map(|&d| format!("{d} {}", tree.distance(d)))
+                .collect();
+            println!(
+                "best clear to the {dir}: ({row}, {col}) scoring {} ({})",
+                tree.scenic_view,
+                dists.join(", ")
+            );
+        }
+    }
+}
+
+/// Why a line of the input isn't part of a rectangular grid of heights
+#[derive(Eq, PartialEq, Clone, Debug)]
+enum ErrorKind {
+    Empty,
+    /// Not the same width as the first row
+    Ragged {
+        want: usize,
+        got: usize,
+    },
+    /// Heights are single digits
+    BadHeight {
+        col: usize,
+        found: char,
+    },
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Empty => write!(f, "no trees"),
+            ErrorKind::Ragged { want, got } => {
+                write!(f, "row is {got} trees wide but the first is {want}")
+            }
+            ErrorKind::BadHeight { col, found } => {
+                write!(f, "`{found}` in column {col} isn't a height from 0 to 9")
+            }
+        }
     }
 }
 
-fn part1() {
-    println!("{}", Forest::new(PART_1_DATA).count_vis());
+#[derive(Eq, PartialEq, Clone, Debug)]
+struct ParseError {
+    line_no: usize,
+    kind: ErrorKind,
 }
 
-fn part2() {
-    println!("{}", Forest::new(PART_1_DATA).scenic_score());
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line_no, self.kind)
+    }
 }
 
+impl std::error::Error for ParseError {}
+
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
 struct Tree {
     height: u8,
     visible: bool,
     scenic_view: usize,
+    /// Whether the tree can be seen from outside the forest on each side, indexed by [`Dir`]
+    visible_from: [bool; 4],
+    /// How many trees it can see looking each way, indexed by [`Dir`]
+    distances: [usize; 4],
+}
+
+impl Tree {
+    fn new(height: u8) -> Self {
+        Tree {
+            height,
+            visible: false,
+            scenic_view: 0,
+            visible_from: [false; 4],
+            distances: [0; 4],
+        }
+    }
+
+    /// Nothing as tall stands between the tree and the edge on this side, so it can be seen
+    /// from there and can see all the way out
+    pub fn visible_from(&self, dir: Dir) -> bool {
+        self.visible_from[dir as usize]
+    }
+
+    pub fn distance(&self, dir: Dir) -> usize {
+        self.distances[dir as usize]
+    }
+
+    pub fn sides_visible(&self) -> usize {
+        self.visible_from.iter().filter(|&&v| v).count()
+    }
+}
+
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+enum Dir {
+    North,
+    East,
+    South,
+    West,
+}
+
+impl Dir {
+    const ALL: [Dir; 4] = [Dir::North, Dir::East, Dir::South, Dir::West];
+}
+
+impl Display for Dir {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Dir::North => "north",
+            Dir::East => "east",
+            Dir::South => "south",
+            Dir::West => "west",
+        };
+        f.write_str(name)
+    }
 }
 
 struct Forest {
@@ -32,28 +231,51 @@ struct Forest {
 }
 
 impl Forest {
-    pub fn new(data: &str) -> Self {
-        let size = data.lines().next().unwrap().len();
-
-        let trees: Vec<Tree> = data
-            .lines()
-            .flat_map(|line| {
-                line.bytes().map(|b| Tree {
-                    height: b - b'0',
-                    visible: false,
-                    scenic_view: 0,
-                })
-            })
-            .collect();
+    pub fn new(data: &str) -> Result<Self, ParseError> {
+        let width = data.lines().next().map_or(0, |line| line.chars().count());
+        if width == 0 {
+            return Err(ParseError {
+                line_no: 1,
+                kind: ErrorKind::Empty,
+            });
+        }
+
+        let mut trees: Vec<Tree> = Vec::with_capacity(data.len());
+        let mut height = 0;
+
+        for (idx, line) in data.lines().enumerate() {
+            let err = |kind| ParseError {
+                line_no: idx + 1,
+                kind,
+            };
+
+            let got = line.chars().count();
+            if got != width {
+                return Err(err(ErrorKind::Ragged { want: width, got }));
+            }
+
+            for (col, c) in line.chars().enumerate() {
+                let height = c.to_digit(10).ok_or_else(|| {
+                    err(ErrorKind::BadHeight {
+                        col: col + 1,
+                        found: c,
+                    })
+                })?;
+
+                trees.push(Tree::new(height as u8));
+            }
+
+            height += 1;
+        }
 
         let mut forest = Forest {
-            trees: Array::from_shape_vec((size, size), trees).unwrap(),
+            trees: Array::from_shape_vec((height, width), trees).unwrap(),
         };
 
         forest.calc_vis();
         forest.update_scenic_score();
 
-        forest
+        Ok(forest)
     }
 
     // fn reset_vis(&mut self) {
@@ -66,85 +288,74 @@ impl Forest {
         for mut row in self.trees.rows_mut() {
             let mut max = None;
             for tree in row.iter_mut() {
-                Forest::adjust(&mut max, tree);
+                Forest::adjust(&mut max, tree, Dir::West);
             }
 
             max = None;
             for tree in row.iter_mut().rev() {
-                Forest::adjust(&mut max, tree);
+                Forest::adjust(&mut max, tree, Dir::East);
             }
         }
 
         for mut col in self.trees.columns_mut() {
             let mut max = None;
             for tree in col.iter_mut() {
-                Forest::adjust(&mut max, tree);
+                Forest::adjust(&mut max, tree, Dir::North);
             }
 
             max = None;
             for tree in col.iter_mut().rev() {
-                Forest::adjust(&mut max, tree);
+                Forest::adjust(&mut max, tree, Dir::South);
             }
         }
     }
 
-    fn adjust(max: &mut Option<u8>, tree: &mut Tree) {
+    /// `from` is the side of the forest the trees are being looked at from
+    fn adjust(max: &mut Option<u8>, tree: &mut Tree, from: Dir) {
         match max {
             Some(h) => {
                 if tree.height > *h {
                     tree.visible = true;
+                    tree.visible_from[from as usize] = true;
                     max.replace(tree.height);
                 }
             }
 
             None => {
                 tree.visible = true;
+                tree.visible_from[from as usize] = true;
                 max.replace(tree.height);
             }
         }
     }
 
+    /// Works out every tree's viewing distance in all four directions with one pass per row and
+    /// column each way, spread across threads
     pub fn update_scenic_score(&mut self) {
-        let t = &mut self.trees;
-        let n = t.nrows();
-
-        for col in 0..n {
-            for row in 0..n {
-                let height = t.get((row, col)).unwrap().height;
-
-                let mut left = 0;
-                let mut up = 0;
-                let mut right = 0;
-                let mut down = 0;
-
-                for c in (0..col).rev() {
-                    left += 1;
-                    if t.get((row, c)).unwrap().height >= height {
-                        break;
-                    }
-                }
-                for c in (col + 1)..n {
-                    right += 1;
-                    if t.get((row, c)).unwrap().height >= height {
-                        break;
-                    }
-                }
+        let heights = self.trees.map(|t| t.height);
+        let (rows, cols) = heights.dim();
 
-                for r in (0..row).rev() {
-                    up += 1;
-                    if t.get((r, col)).unwrap().height >= height {
-                        break;
-                    }
-                }
-                for r in (row + 1)..n {
-                    down += 1;
-                    if t.get((r, col)).unwrap().height >= height {
-                        break;
-                    }
-                }
+        // How far each tree can see west and east, one row at a time
+        let across: Vec<(Vec<usize>, Vec<usize>)> = (0..rows)
+            .into_par_iter()
+            .map(|row| both_ways(heights.row(row).iter().copied()))
+            .collect();
 
-                t.get_mut((row, col)).unwrap().scenic_view = left * up * right * down;
-            }
+        // And north and south, one column at a time
+        let down: Vec<(Vec<usize>, Vec<usize>)> = (0..cols)
+            .into_par_iter()
+            .map(|col| both_ways(heights.column(col).iter().copied()))
+            .collect();
+
+        for ((row, col), tree) in self.trees.indexed_iter_mut() {
+            let (left, right) = &across[row];
+            let (up, down) = &down[col];
+
+            tree.distances[Dir::North as usize] = up[row];
+            tree.distances[Dir::East as usize] = right[col];
+            tree.distances[Dir::South as usize] = down[row];
+            tree.distances[Dir::West as usize] = left[col];
+            tree.scenic_view = tree.distances.iter().product();
         }
     }
 
@@ -155,6 +366,62 @@ impl Forest {
     pub fn scenic_score(&self) -> usize {
         self.trees.iter().map(|t| t.scenic_view).max().unwrap()
     }
+
+    /// Every tree along with its `(row, col)`, row by row
+    pub fn trees(&self) -> impl Iterator<Item = ((usize, usize), &Tree)> {
+        self.trees.indexed_iter()
+    }
+
+    /// The trees that can be seen from exactly `sides` sides of the forest
+    pub fn visible_from_sides(
+        &self,
+        sides: usize,
+    ) -> impl Iterator<Item = ((usize, usize), &Tree)> {
+        self.trees()
+            .filter(move |(_, t)| t.sides_visible() == sides)
+    }
+
+    /// The tree with the best scenic score out of those that match, the first one found if
+    /// there's a tie
+    pub fn best_where(&self, pred: impl Fn(&Tree) -> bool) -> Option<((usize, usize), &Tree)> {
+        self.trees()
+            .filter(|(_, t)| pred(t))
+            .fold(None, |best, (pos, t)| match best {
+                Some((_, b)) if b.scenic_view >= t.scenic_view => best,
+                _ => Some((pos, t)),
+            })
+    }
+}
+
+/// Viewing distances looking back towards the start and ahead towards the end of a line of trees
+fn both_ways(heights: impl DoubleEndedIterator<Item = u8> + Clone) -> (Vec<usize>, Vec<usize>) {
+    let back = viewing_distances(heights.clone());
+    let mut ahead = viewing_distances(heights.rev());
+    ahead.reverse();
+
+    (back, ahead)
+}
+
+/// How many trees each tree can see looking back towards the start of the line, stopping at the
+/// first one at least as tall.  The stack holds the trees that could still block a later view,
+/// tallest at the bottom, so each tree is pushed and popped at most once
+fn viewing_distances(heights: impl Iterator<Item = u8>) -> Vec<usize> {
+    let mut dists = Vec::new();
+    let mut stack: Vec<(usize, u8)> = Vec::new();
+
+    for (idx, height) in heights.enumerate() {
+        while stack.last().is_some_and(|&(_, h)| h < height) {
+            stack.pop();
+        }
+
+        dists.push(match stack.last() {
+            Some(&(blocker, _)) => idx - blocker,
+            None => idx,
+        });
+        stack.push((idx, height));
+    }
+
+    dists
 }
 
 #[cfg(test)]
@@ -173,7 +440,7 @@ mod tests {
 
     #[test]
     fn examples() {
-        let f = Forest::new(test_data());
+        let f = Forest::new(test_data()).unwrap();
 
         let mut h = String::with_capacity(10);
         let mut s = String::with_capacity(10);
@@ -192,4 +459,156 @@ mod tests {
         assert_eq!(21, f.count_vis());
         assert_eq!(8, f.scenic_score())
     }
+
+    /// The original walk outwards from every tree, to check the stacks against
+    fn naive_scenic_views(f: &Forest) -> Vec<usize> {
+        let t = &f.trees;
+        let (rows, cols) = t.dim();
+        let mut views = Vec::with_capacity(rows * cols);
+
+        for row in 0..rows {
+            for col in 0..cols {
+                let height = t[(row, col)].height;
+                let see = |trees: &mut dyn Iterator<Item = (usize, usize)>| {
+                    let mut dist = 0;
+                    for pos in trees {
+                        dist += 1;
+                        if t[pos].height >= height {
+                            break;
+                        }
+                    }
+                    dist
+                };
+
+                views.push(
+                    see(&mut (0..col).rev().map(|c| (row, c)))
+                        * see(&mut ((col + 1)..cols).map(|c| (row, c)))
+                        * see(&mut (0..row).rev().map(|r| (r, col)))
+                        * see(&mut ((row + 1)..rows).map(|r| (r, col))),
+                );
+            }
+        }
+
+        views
+    }
+
+    /// A reproducible forest of any size, without pulling in a random number crate
+    fn generated(rows: usize, cols: usize, seed: u64) -> String {
+        let mut state = seed;
+        let mut data = String::with_capacity(rows * (cols + 1));
+
+        for _ in 0..rows {
+            for _ in 0..cols {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                data.push(char::from(b'0' + ((state >> 33) % 10) as u8));
+            }
+            data.push('\n');
+        }
+
+        data
+    }
+
+    #[test]
+    fn scenic_matches_naive() {
+        for data in [
+            test_data().to_string(),
+            PART_1_DATA.to_string(),
+            generated(120, 200, 1),
+            generated(200, 120, 2),
+            generated(1, 50, 3),
+        ] {
+            let f = Forest::new(&data).unwrap();
+            let views: Vec<usize> = f.trees.iter().map(|t| t.scenic_view).collect();
+            assert_eq!(naive_scenic_views(&f), views);
+        }
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(
+            vec![0, 1, 2, 1, 4],
+            viewing_distances([3, 0, 3, 1, 5].into_iter())
+        );
+        assert_eq!(
+            (vec![0, 1, 2, 1, 4], vec![2, 1, 2, 1, 0]),
+            both_ways([3, 0, 3, 1, 5].into_iter())
+        );
+    }
+
+    #[test]
+    fn directions() {
+        let f = Forest::new(test_data()).unwrap();
+
+        // The middle 5 in the second row is visible from the top and the right
+        let t = &f.trees[(1, 2)];
+        assert!(t.visible_from(Dir::North) && t.visible_from(Dir::East));
+        assert!(!t.visible_from(Dir::South) && !t.visible_from(Dir::West));
+        assert_eq!(2, t.sides_visible());
+
+        // The 5 in the middle of the fourth row from the example
+        let t = &f.trees[(3, 2)];
+        assert_eq!([2, 2, 1, 2], Dir::ALL.map(|d| t.distance(d)));
+        assert_eq!(8, t.scenic_view);
+
+        let corner = &f.trees[(0, 0)];
+        assert_eq!(2, corner.sides_visible());
+        assert_eq!(0, corner.distance(Dir::North));
+
+        let counts: Vec<usize> = (0..=4).map(|k| f.visible_from_sides(k).count()).collect();
+        assert_eq!(vec![4, 9, 8, 1, 3], counts);
+        assert_eq!(25, counts.iter().sum::<usize>());
+        assert_eq!(f.count_vis(), 25 - counts[0]);
+
+        let ((row, col), best) = f.best_where(|t| t.visible_from(Dir::North)).unwrap();
+        assert_eq!(((1, 2), 4), ((row, col), best.scenic_view));
+        let ((row, col), best) = f.best_where(|t| !t.visible_from(Dir::North)).unwrap();
+        assert_eq!(((3, 2), 8), ((row, col), best.scenic_view));
+        assert!(f.best_where(|t| t.height > 9).is_none());
+    }
+
+    #[test]
+    fn rectangles() {
+        let wide = Forest::new("30373\n25512\n65332").unwrap();
+        assert_eq!((3, 5), wide.trees.dim());
+        assert_eq!(14, wide.count_vis());
+        assert_eq!(2, wide.scenic_score());
+
+        // Turning the grid on its side mustn't change anything
+        let tall = Forest::new("326\n055\n353\n713\n322").unwrap();
+        assert_eq!((5, 3), tall.trees.dim());
+        assert_eq!(14, tall.count_vis());
+        assert_eq!(2, tall.scenic_score());
+
+        let line = Forest::new("12321").unwrap();
+        assert_eq!(5, line.count_vis());
+        assert_eq!(0, line.scenic_score());
+    }
+
+    #[test]
+    fn bad_input() {
+        for (data, line_no, kind) in [
+            ("", 1, ErrorKind::Empty),
+            ("\n123", 1, ErrorKind::Empty),
+            ("123\n12\n123", 2, ErrorKind::Ragged { want: 3, got: 2 }),
+            ("123\n123\n\n", 3, ErrorKind::Ragged { want: 3, got: 0 }),
+            ("123\n1a3", 2, ErrorKind::BadHeight { col: 2, found: 'a' }),
+            ("12 ", 1, ErrorKind::BadHeight { col: 3, found: ' ' }),
+        ] {
+            assert_eq!(
+                Err(ParseError { line_no, kind }),
+                Forest::new(data).map(|_| ()),
+                "{data:?}"
+            );
+        }
+
+        // Windows line endings are fine
+        assert_eq!(
+            21,
+            Forest::new(&test_data().replace('\n', "\r\n"))
+                .unwrap()
+                .count_vis()
+        );
+    }
 }
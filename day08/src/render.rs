@@ -0,0 +1,201 @@
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+use crate::{Forest, Tree};
+
+/// What colour each tree is painted
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Layer {
+    /// Grayscale, black for 0 up to white for 9
+    Height,
+    /// Heights in grayscale, with the trees that can be seen from outside in green
+    Visible,
+    /// Scenic scores from black through purple and red to yellow.  Scores cover several orders
+    /// of magnitude so the ramp follows their log
+    Scenic,
+}
+
+pub type Rgb = [u8; 3];
+
+/// Stops along the scenic score ramp, evenly spaced
+const RAMP: [Rgb; 5] = [
+    [0, 0, 0],
+    [80, 0, 120],
+    [220, 40, 40],
+    [255, 200, 0],
+    [255, 255, 220],
+];
+
+/// A picture of a forest, one block of pixels per tree
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Row by row from the top left
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    /// Binary PPM (`P6`), which almost anything can open or convert
+    pub fn write_ppm<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.pixels.concat())
+    }
+
+    pub fn write_png<W: Write>(&self, out: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels.concat())
+    }
+}
+
+impl Forest {
+    /// Paints `layer` with each tree as a `scale` by `scale` square
+    pub fn render(&self, layer: Layer, scale: usize) -> Image {
+        let (rows, cols) = self.trees.dim();
+        let (width, height) = (cols * scale, rows * scale);
+        let colour = self.colourer(layer);
+
+        let mut pixels = Vec::with_capacity(width * height);
+        for row in self.trees.rows() {
+            let line: Vec<Rgb> = row
+                .iter()
+                .flat_map(|tree| std::iter::repeat_n(colour(tree), scale))
+                .collect();
+            for _ in 0..scale {
+                pixels.extend_from_slice(&line);
+            }
+        }
+
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// The heights as digits on a 24-bit colour background, for terminals that support it
+    pub fn ansi(&self, layer: Layer) -> String {
+        let colour = self.colourer(layer);
+        let mut out = String::with_capacity(self.trees.len() * 40);
+
+        for row in self.trees.rows() {
+            for tree in row.iter() {
+                let [r, g, b] = colour(tree);
+                // Dark digits on light backgrounds and the other way round
+                let fg = if luma([r, g, b]) > 128 { 0 } else { 255 };
+                write!(
+                    out,
+                    "\x1b[48;2;{r};{g};{b}m\x1b[38;2;{fg};{fg};{fg}m{}",
+                    tree.height
+                )
+                .unwrap();
+            }
+            out.push_str("\x1b[0m\n");
+        }
+
+        out
+    }
+
+    fn colourer(&self, layer: Layer) -> impl Fn(&Tree) -> Rgb {
+        let max_score = self.scenic_score();
+
+        move |tree: &Tree| match layer {
+            Layer::Height => gray(tree.height),
+            Layer::Visible if tree.visible => [0, scale(tree.height, 100, 255), 0],
+            Layer::Visible => gray(tree.height),
+            Layer::Scenic => {
+                let frac = if max_score == 0 {
+                    0.0
+                } else {
+                    (tree.scenic_view as f64).ln_1p() / (max_score as f64).ln_1p()
+                };
+                ramp(frac)
+            }
+        }
+    }
+}
+
+fn gray(height: u8) -> Rgb {
+    [scale(height, 0, 255); 3]
+}
+
+/// Spreads a height from 0 to 9 over `lo..=hi`
+fn scale(height: u8, lo: u8, hi: u8) -> u8 {
+    (lo as u32 + height.min(9) as u32 * (hi - lo) as u32 / 9) as u8
+}
+
+/// Blends between the two [`RAMP`] stops either side of `frac`, which runs from 0 to 1
+fn ramp(frac: f64) -> Rgb {
+    let pos = frac.clamp(0.0, 1.0) * (RAMP.len() - 1) as f64;
+    let idx = (pos.floor() as usize).min(RAMP.len() - 2);
+    let t = pos - idx as f64;
+
+    let (from, to) = (RAMP[idx], RAMP[idx + 1]);
+    [0, 1, 2].map(|c| (from[c] as f64 + (to[c] as f64 - from[c] as f64) * t).round() as u8)
+}
+
+fn luma([r, g, b]: Rgb) -> u32 {
+    (299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn images() {
+        let f = Forest::new("305\n255").unwrap();
+
+        let img = f.render(Layer::Height, 2);
+        assert_eq!((6, 4), (img.width, img.height));
+        assert_eq!(
+            vec![[85; 3], [85; 3], [0; 3], [0; 3], [141; 3], [141; 3]],
+            img.pixels[..6]
+        );
+        assert_eq!(img.pixels[..6], img.pixels[6..12]);
+
+        let mut ppm = Vec::new();
+        f.render(Layer::Height, 1).write_ppm(&mut ppm).unwrap();
+        assert_eq!(b"P6\n3 2\n255\n", &ppm[..11]);
+        assert_eq!(11 + 3 * 6, ppm.len());
+
+        let mut png = Vec::new();
+        img.write_png(&mut png).unwrap();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+
+        // Only the 0 in the middle is hidden
+        let img = Forest::new("305\n202\n555")
+            .unwrap()
+            .render(Layer::Visible, 1);
+        assert_eq!([0, 151, 0], img.pixels[0]);
+        assert_eq!([0, 100, 0], img.pixels[1]);
+        assert_eq!([0; 3], img.pixels[4]);
+
+        // Edges all score 0 so the whole picture is the bottom of the ramp
+        let img = f.render(Layer::Scenic, 1);
+        assert!(img.pixels.iter().all(|&p| p == RAMP[0]));
+    }
+
+    #[test]
+    fn ramps() {
+        assert_eq!(RAMP[0], ramp(0.0));
+        assert_eq!(RAMP[2], ramp(0.5));
+        assert_eq!(RAMP[4], ramp(1.0));
+        assert_eq!(RAMP[4], ramp(7.0));
+        assert_eq!([150, 20, 80], ramp(0.375));
+    }
+
+    #[test]
+    fn ansi() {
+        let f = Forest::new("19").unwrap();
+        assert_eq!(
+            "\x1b[48;2;28;28;28m\x1b[38;2;255;255;255m1\x1b[48;2;255;255;255m\x1b[38;2;0;0;0m9\x1b[0m\n",
+            f.ansi(Layer::Height)
+        );
+        assert_eq!(1, f.ansi(Layer::Scenic).lines().count());
+    }
+}
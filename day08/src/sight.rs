@@ -0,0 +1,267 @@
+use rayon::prelude::*;
+
+use crate::Forest;
+
+/// Someone looking at the forest.  Positions are in trees, with the middle of the tree at
+/// `(row, col)` sitting at `(row as f64, col as f64)`, so they can be anywhere including off the
+/// edge of the grid.  `eye` is measured in the same units as tree heights
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Observer {
+    pub row: f64,
+    pub col: f64,
+    pub eye: f64,
+}
+
+impl Observer {
+    pub fn new(row: f64, col: f64, eye: f64) -> Self {
+        Observer { row, col, eye }
+    }
+
+    /// Standing on top of a `tower` built on the tree at `pos`
+    pub fn on_tree(forest: &Forest, pos: (usize, usize), tower: f64) -> Self {
+        let height = forest.trees[pos].height as f64;
+        Observer::new(pos.0 as f64, pos.1 as f64, height + tower)
+    }
+
+    /// Where the ray from the observer along `dir` is after `t` lengths of it
+    fn at(&self, dir: (f64, f64), t: f64) -> (f64, f64) {
+        (self.row + dir.0 * t, self.col + dir.1 * t)
+    }
+
+    fn cell(&self) -> (i64, i64) {
+        (self.row.round() as i64, self.col.round() as i64)
+    }
+
+    /// How steeply the observer has to look up (or down, if negative) to see the top of a tree
+    fn slope(&self, (row, col): (usize, usize), height: u8) -> f64 {
+        let dist = (row as f64 - self.row).hypot(col as f64 - self.col);
+        (height as f64 - self.eye) / dist
+    }
+}
+
+impl Forest {
+    /// Whether the top of the tree at `target` can be seen.  Trees count as thin poles in the
+    /// middle of their square, and one blocks the view if the ray passes through its square and
+    /// the observer would have to look at least as high to see over it.  The tree the observer
+    /// is standing on doesn't block anything and can't be seen itself
+    pub fn can_see(&self, obs: &Observer, target: (usize, usize)) -> bool {
+        let start = obs.cell();
+        let end = (target.0 as i64, target.1 as i64);
+        if start == end {
+            return false;
+        }
+
+        let want = obs.slope(target, self.trees[target].height);
+        let dir = (target.0 as f64 - obs.row, target.1 as f64 - obs.col);
+        let Some((t_start, _)) = self.span(obs, dir) else {
+            return false;
+        };
+
+        !cells(obs.at(dir, t_start), dir, 1.0 - t_start)
+            .filter(|&cell| cell != start && cell != end)
+            .filter_map(|cell| self.in_grid(cell))
+            .any(|pos| obs.slope(pos, self.trees[pos].height) >= want)
+    }
+
+    /// Every tree the observer can see, row by row
+    pub fn seen_by(&self, obs: &Observer) -> Vec<(usize, usize)> {
+        let cols = self.trees.ncols();
+
+        (0..self.trees.len())
+            .into_par_iter()
+            .map(|idx| (idx / cols, idx % cols))
+            .filter(|&pos| self.can_see(obs, pos))
+            .collect()
+    }
+
+    /// The trees that can be seen looking along a single ray in direction `(rows, cols)`,
+    /// nearest first.  Works like a horizon: a tree shows if it rises above everything nearer.
+    /// A direction of no length, or one that isn't finite, sees nothing
+    pub fn look(&self, obs: &Observer, dir: (f64, f64)) -> Vec<(usize, usize)> {
+        if !(dir.0.is_finite() && dir.1.is_finite()) || dir == (0.0, 0.0) {
+            return Vec::new();
+        }
+
+        let Some((t_start, t_end)) = self.span(obs, dir) else {
+            return Vec::new();
+        };
+
+        let start = obs.cell();
+        let mut horizon = f64::NEG_INFINITY;
+        let mut seen = Vec::new();
+
+        for pos in cells(obs.at(dir, t_start), dir, t_end - t_start)
+            .filter(|&cell| cell != start)
+            .filter_map(|cell| self.in_grid(cell))
+        {
+            let slope = obs.slope(pos, self.trees[pos].height);
+            if slope > horizon {
+                seen.push(pos);
+                horizon = slope;
+            }
+        }
+
+        seen
+    }
+
+    fn in_grid(&self, (row, col): (i64, i64)) -> Option<(usize, usize)> {
+        let (rows, cols) = self.trees.dim();
+        let pos = (usize::try_from(row).ok()?, usize::try_from(col).ok()?);
+        (pos.0 < rows && pos.1 < cols).then_some(pos)
+    }
+
+    /// How far along `dir` the ray is when it first reaches the grid and when it leaves it for
+    /// good, if it ever touches it
+    fn span(&self, obs: &Observer, dir: (f64, f64)) -> Option<(f64, f64)> {
+        let (rows, cols) = self.trees.dim();
+        let mut t_range = (0.0, f64::INFINITY);
+
+        for (pos, d, len) in [(obs.row, dir.0, rows), (obs.col, dir.1, cols)] {
+            let (lo, hi) = (-0.5, len as f64 - 0.5);
+            if d == 0.0 {
+                if pos < lo || pos >= hi {
+                    return None;
+                }
+                continue;
+            }
+
+            let (a, b) = ((lo - pos) / d, (hi - pos) / d);
+            t_range.0 = f64::max(t_range.0, a.min(b));
+            t_range.1 = f64::min(t_range.1, a.max(b));
+        }
+
+        (t_range.0 < t_range.1 && t_range.1.is_finite()).then_some(t_range)
+    }
+}
+
+/// Every square the ray from `from` along `dir` passes through up to `t_end` lengths of `dir`,
+/// in order, by stepping from one square edge to the next.  When the ray goes exactly
+/// through a corner it steps diagonally rather than touching either side
+fn cells(from: (f64, f64), dir: (f64, f64), t_end: f64) -> impl Iterator<Item = (i64, i64)> {
+    const EPS: f64 = 1e-9;
+
+    // Shift so that squares run from one whole number to the next
+    let pos = [from.0 + 0.5, from.1 + 0.5];
+    let dir = [dir.0, dir.1];
+
+    let mut cell = pos.map(|p| p.floor() as i64);
+    let step = dir.map(|d| if d > 0.0 { 1 } else { -1 });
+    let delta = dir.map(|d| 1.0 / d.abs());
+    let mut next = [0, 1].map(|axis| {
+        let p = pos[axis];
+        match dir[axis] {
+            d if d > 0.0 => (p.floor() + 1.0 - p) / d,
+            d if d < 0.0 => (p - p.floor()) / -d,
+            _ => f64::INFINITY,
+        }
+    });
+
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+
+        let here = (cell[0], cell[1]);
+        let t = next[0].min(next[1]);
+        if t > t_end - EPS {
+            done = true;
+        } else {
+            for axis in 0..2 {
+                if next[axis] <= t + EPS {
+                    cell[axis] += step[axis];
+                    next[axis] += delta[axis];
+                }
+            }
+        }
+
+        Some(here)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(obs: Observer, dir: (f64, f64), t_end: f64) -> Vec<(i64, i64)> {
+        cells((obs.row, obs.col), dir, t_end).collect()
+    }
+
+    #[test]
+    fn casting() {
+        let obs = Observer::new(2.0, 2.0, 0.0);
+        assert_eq!(
+            vec![(2, 2), (2, 3), (2, 4), (2, 5)],
+            path(obs, (0.0, 3.0), 1.0)
+        );
+        assert_eq!(
+            vec![(2, 2), (1, 1), (0, 0), (-1, -1)],
+            path(obs, (-3.0, -3.0), 1.0)
+        );
+        assert_eq!(
+            vec![(2, 2), (2, 3), (3, 3), (3, 4), (4, 4)],
+            path(obs, (2.0, 2.2), 1.0)
+        );
+    }
+
+    #[test]
+    fn along_a_row() {
+        let f = Forest::new("13121").unwrap();
+
+        let ground = Observer::new(0.0, -1.0, 0.0);
+        assert_eq!(vec![(0, 0), (0, 1)], f.look(&ground, (0.0, 1.0)));
+        assert_eq!(vec![(0, 0), (0, 1)], f.seen_by(&ground));
+        assert!(!f.can_see(&ground, (0, 3)));
+
+        let high = Observer::new(0.0, -1.0, 100.0);
+        assert_eq!(5, f.look(&high, (0.0, 1.0)).len());
+        assert_eq!(5, f.seen_by(&high).len());
+
+        // Far enough away that walking every square on the way would never finish
+        let far = Observer::new(0.0, -1e9, 0.0);
+        assert_eq!(vec![(0, 0), (0, 1)], f.seen_by(&far));
+        assert_eq!(vec![(0, 0), (0, 1)], f.look(&far, (0.0, 1.0)));
+        let above = Observer::new(-1e9, 2.0, 1e10);
+        assert_eq!(5, f.seen_by(&above).len());
+
+        // Looking away from the forest, or past it
+        assert!(f.look(&ground, (0.0, -1.0)).is_empty());
+        assert!(f.look(&ground, (1.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn no_direction() {
+        let f = Forest::new("123\n456").unwrap();
+        let inside = Observer::new(0.0, 0.0, 1.0);
+
+        assert!(f.look(&inside, (0.0, 0.0)).is_empty());
+        assert!(f.look(&inside, (f64::NAN, 1.0)).is_empty());
+        assert!(f.look(&inside, (0.0, f64::INFINITY)).is_empty());
+        assert_eq!(None, f.span(&inside, (0.0, 0.0)));
+        assert_eq!(vec![(0, 1)], f.look(&inside, (0.0, 1.0)));
+    }
+
+    #[test]
+    fn diagonals() {
+        let f = Forest::new("100\n090\n001").unwrap();
+
+        let obs = Observer::new(-1.0, -1.0, 1.5);
+        assert_eq!(vec![(0, 0), (1, 1)], f.look(&obs, (1.0, 1.0)));
+        assert!(f.can_see(&obs, (1, 1)));
+        assert!(!f.can_see(&obs, (2, 2)));
+        assert!(f.can_see(&obs, (0, 2)));
+
+        // On top of the 9 everything around is in view
+        let tower = Observer::on_tree(&f, (1, 1), 0.5);
+        assert_eq!(9.5, tower.eye);
+        assert_eq!(8, f.seen_by(&tower).len());
+        assert!(!f.seen_by(&tower).contains(&(1, 1)));
+
+        // From the corner the 9 hides everything whose ray passes through its square
+        let corner = Observer::on_tree(&f, (0, 0), 0.0);
+        assert_eq!(
+            vec![(0, 1), (0, 2), (1, 0), (1, 1), (2, 0)],
+            f.seen_by(&corner)
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Debug;
+
+use crate::Coord;
+
+/// How a knot moves once the knot ahead of it has, in `D` dimensions
+pub trait FollowRule<const D: usize = 2>: Debug {
+    /// Where a knot at `tail` ends up after the knot ahead of it moved to `head`
+    fn follow(&self, tail: Coord<D>, head: Coord<D>) -> Coord<D>;
+}
+
+impl<R: FollowRule<D> + ?Sized, const D: usize> FollowRule<D> for &R {
+    fn follow(&self, tail: Coord<D>, head: Coord<D>) -> Coord<D> {
+        (**self).follow(tail, head)
+    }
+}
+
+/// The puzzle's rule: stay touching, including diagonally, by stepping straight or diagonally
+/// towards the knot ahead
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Standard;
+
+impl<const D: usize> FollowRule<D> for Standard {
+    fn follow(&self, mut tail: Coord<D>, head: Coord<D>) -> Coord<D> {
+        tail.adjust_tail(head);
+        tail
+    }
+}
+
+/// Knots can only step along one axis at a time, so when the one ahead pulls away diagonally
+/// they close the widest gap first and can fall behind
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Orthogonal;
+
+impl<const D: usize> FollowRule<D> for Orthogonal {
+    fn follow(&self, mut tail: Coord<D>, head: Coord<D>) -> Coord<D> {
+        if tail.distance(head) <= 1 {
+            return tail;
+        }
+
+        // The first of the widest axes if there's a tie
+        let axis = (0..D)
+            .rev()
+            .max_by_key(|&axis| (head.0[axis] - tail.0[axis]).abs())
+            .unwrap();
+        tail.0[axis] += (head.0[axis] - tail.0[axis]).signum();
+        tail
+    }
+}
+
+/// Like [`Standard`] but with this many cells of slack, so knots only move once they are more
+/// than one further than that away.  `Slack(0)` is the same as [`Standard`]
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Slack(pub i32);
+
+impl<const D: usize> FollowRule<D> for Slack {
+    fn follow(&self, mut tail: Coord<D>, head: Coord<D>) -> Coord<D> {
+        if tail.distance(head) <= 1 + self.0 {
+            return tail;
+        }
+
+        for (pos, to) in tail.0.iter_mut().zip(head.0) {
+            *pos += (to - *pos).signum();
+        }
+        tail
+    }
+}
+
+/// Knots lag while the rope stretches by up to this many cells, then snap back to the nearest
+/// cell touching the knot ahead in one go
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Elastic(pub i32);
+
+impl<const D: usize> FollowRule<D> for Elastic {
+    fn follow(&self, mut tail: Coord<D>, head: Coord<D>) -> Coord<D> {
+        if tail.distance(head) <= 1 + self.0 {
+            return tail;
+        }
+
+        for (pos, to) in tail.0.iter_mut().zip(head.0) {
+            *pos = (*pos).clamp(to - 1, to + 1);
+        }
+        tail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::knots;
+    use crate::{simulate_with, Rope};
+
+    const EXAMPLE: &str = "R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2";
+
+    fn tail(data: &str, len: usize, rule: impl FollowRule) -> (Coord, usize) {
+        let rope = simulate_with(data, knots(len), rule).unwrap();
+        (rope.knots[len - 1], rope.tail_visited())
+    }
+
+    #[test]
+    fn standard() {
+        assert_eq!(13, tail(EXAMPLE, 2, Standard).1);
+        assert_eq!(tail(EXAMPLE, 2, Standard), tail(EXAMPLE, 2, Slack(0)));
+        assert_eq!(tail(EXAMPLE, 10, Standard), tail(EXAMPLE, 10, Slack(0)));
+    }
+
+    #[test]
+    fn orthogonal() {
+        assert_eq!((Coord([4, 3]), 7), tail("R 4\nU 4", 2, Standard));
+        assert_eq!((Coord([3, 3]), 7), tail("R 4\nU 4", 2, Orthogonal));
+
+        // Heading off diagonally the tail can't keep up and gets left further behind
+        assert_eq!((Coord([2, 1]), 4), tail("UR 4", 2, Orthogonal));
+    }
+
+    #[test]
+    fn in_3d() {
+        let rope: Rope<Orthogonal, 3> = simulate_with("URF 2", knots(2), Orthogonal).unwrap();
+        assert_eq!(vec![Coord([2, 2, 2]), Coord([1, 0, 0])], rope.knots);
+
+        let rope: Rope<Elastic, 3> = simulate_with("URF 3", knots(2), Elastic(1)).unwrap();
+        assert_eq!(vec![Coord([3, 3, 3]), Coord([2, 2, 2])], rope.knots);
+    }
+
+    #[test]
+    fn stretchy() {
+        assert_eq!((Coord([4, 0]), 5), tail("R 5", 2, Standard));
+        assert_eq!((Coord([3, 0]), 4), tail("R 5", 2, Slack(1)));
+        assert_eq!((Coord([4, 0]), 3), tail("R 5", 2, Elastic(1)));
+
+        // Snapping back lands on the nearest touching cell, which can be diagonally behind
+        assert_eq!((Coord([3, 2]), 5), tail("R 3\nU 3", 2, Standard));
+        assert_eq!((Coord([2, 2]), 3), tail("R 3\nU 3", 2, Elastic(1)));
+        assert_eq!((Coord([2, 1]), 3), tail("R 3\nU 3", 2, Slack(1)));
+    }
+}
@@ -1,147 +1,328 @@
+mod follow;
+mod parser;
+mod render;
+
+use crate::follow::{Elastic, FollowRule, Orthogonal, Slack, Standard};
+use crate::parser::{parse_moves, Move, ParseError};
 use std::collections::HashSet;
+use std::num::NonZeroUsize;
+
+const PART_1: bool = false;
+
+/// Print the rope after every step of the first example, then where its tail went
+const ANIMATE: bool = false;
+/// Print how many places the tail visits under each follow rule
+const COMPARE_RULES: bool = false;
 
-const PART_1: bool = true;
+const SHORT_ROPE: NonZeroUsize = NonZeroUsize::new(2).unwrap();
+const LONG_ROPE: NonZeroUsize = NonZeroUsize::new(10).unwrap();
 
 static PART_1_DATA: &str = include_str!("input");
 
-fn main() {
+fn main() -> Result<(), ParseError> {
+    if ANIMATE {
+        animate()?;
+    }
+    if COMPARE_RULES {
+        compare_rules()?;
+    }
+
     if PART_1 {
-        part1();
+        part1()
     } else {
-        part2();
+        part2()
     }
 }
 
-fn part1() {
-    let mut pos = HashSet::with_capacity(1024);
-    pos.insert(Coord::default());
+fn part1() -> Result<(), ParseError> {
+    println!("{}", simulate(PART_1_DATA, SHORT_ROPE)?.tail_visited());
+    Ok(())
+}
 
-    let mut knot = Knot::default();
+fn part2() -> Result<(), ParseError> {
+    println!("{}", simulate(PART_1_DATA, LONG_ROPE)?.tail_visited());
+    Ok(())
+}
 
-    for line in PART_1_DATA.lines() {
-        knot.do_moves(line, &mut pos);
+fn animate() -> Result<(), ParseError> {
+    let moves = parse_moves("R 4\nU 4\nL 3\nD 1\nR 4\nD 1\nL 5\nR 2")?;
+    let mut rope = Rope::new(LONG_ROPE);
+
+    for knots in rope.steps(&moves) {
+        println!("{}", render::draw(&knots));
     }
+    println!(
+        "{}",
+        render::draw_visited(&rope.visited[LONG_ROPE.get() - 1])
+    );
 
-    println!("{}", pos.len());
+    Ok(())
 }
 
-fn part2() {}
+fn compare_rules() -> Result<(), ParseError> {
+    let rules: [&dyn FollowRule; 6] = [
+        &Standard,
+        &Orthogonal,
+        &Slack(1),
+        &Slack(3),
+        &Elastic(1),
+        &Elastic(3),
+    ];
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug, Default)]
-struct Coord {
-    x: i32,
-    y: i32,
+    println!("rule\t2 knots\t10 knots");
+    for rule in rules {
+        let short = simulate_with(PART_1_DATA, SHORT_ROPE, rule)?;
+        let long = simulate_with(PART_1_DATA, LONG_ROPE, rule)?;
+        println!(
+            "{rule:?}\t{}\t{}",
+            short.tail_visited(),
+            long.tail_visited()
+        );
+    }
+
+    Ok(())
 }
 
-impl Coord {
-    fn move_head(&mut self, dir: Dir) {
-        use Dir::*;
+/// Runs every move in `data` on a 2D rope with `knots` knots, counting the head
+fn simulate(data: &str, knots: NonZeroUsize) -> Result<Rope, ParseError> {
+    simulate_with(data, knots, Standard)
+}
 
-        match dir {
-            Up => self.y += 1,
-            Right => self.x += 1,
-            Down => self.y -= 1,
-            Left => self.x -= 1,
-        }
+/// The same with any follow rule, in `D` dimensions
+fn simulate_with<R: FollowRule<D>, const D: usize>(
+    data: &str,
+    knots: NonZeroUsize,
+    rule: R,
+) -> Result<Rope<R, D>, ParseError> {
+    let mut rope = Rope::with_rule(knots, rule);
+
+    for m in parse_moves(data)? {
+        rope.do_move(m);
     }
 
-    fn adjust_tail(&mut self, head: Coord) {
-        let x_diff = (self.x - head.x).abs();
-        let y_diff = (self.y - head.y).abs();
+    Ok(rope)
+}
 
-        if x_diff > 1 {
-            if self.x < head.x {
-                self.x += 1;
-            } else {
-                self.x -= 1;
-            }
+/// A point on a grid with `D` axes, `x` then `y` then `z` and so on
+#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
+struct Coord<const D: usize = 2>([i32; D]);
 
-            if y_diff > 0 {
-                if self.y < head.y {
-                    self.y += 1;
-                } else {
-                    self.y -= 1;
-                }
-            }
-        } else if y_diff > 1 {
-            if self.y < head.y {
-                self.y += 1;
-            } else {
-                self.y -= 1;
-            }
+impl<const D: usize> Default for Coord<D> {
+    fn default() -> Self {
+        Coord([0; D])
+    }
+}
+
+impl<const D: usize> Coord<D> {
+    fn move_head(&mut self, dir: Dir<D>) {
+        for (pos, step) in self.0.iter_mut().zip(dir.0) {
+            *pos += step;
+        }
+    }
 
-            if x_diff > 0 {
-                if self.x < head.x {
-                    self.x += 1;
-                } else {
-                    self.x -= 1;
-                }
+    /// Once the head is more than one away along any axis, steps towards it by one along every
+    /// axis they differ in, so diagonally if need be
+    fn adjust_tail(&mut self, head: Coord<D>) {
+        if self.distance(head) > 1 {
+            for (pos, to) in self.0.iter_mut().zip(head.0) {
+                *pos += (to - *pos).signum();
             }
         }
     }
-}
 
-const TAIL_N: usize = 9;
+    /// How many king's moves apart two points are
+    fn distance(self, other: Coord<D>) -> i32 {
+        self.0
+            .iter()
+            .zip(other.0)
+            .map(|(a, b)| (a - b).abs())
+            .max()
+            .unwrap_or(0)
+    }
+}
 
-#[derive(Eq, PartialEq, Copy, Clone, Hash, Debug, Default)]
-struct Knot {
-    head: Coord,
-    tails: [Coord; TAIL_N],
+/// A rope made of knots, the first being the head, along with everywhere each knot has been.
+/// Each knot moves after the one ahead of it according to `rule`
+#[derive(Eq, PartialEq, Clone, Debug)]
+struct Rope<R = Standard, const D: usize = 2> {
+    knots: Vec<Coord<D>>,
+    visited: Vec<HashSet<Coord<D>>>,
+    rule: R,
 }
 
+/// One step for the head, -1, 0 or 1 along each axis
 #[derive(Eq, PartialEq, Copy, Clone, Hash, Debug)]
-enum Dir {
-    Up,
-    Right,
-    Down,
-    Left,
+struct Dir<const D: usize = 2>([i32; D]);
+
+impl Rope {
+    fn new(knots: NonZeroUsize) -> Self {
+        Rope::with_rule(knots, Standard)
+    }
 }
 
-impl From<char> for Dir {
-    fn from(c: char) -> Self {
-        use Dir::*;
+impl<R: FollowRule<D>, const D: usize> Rope<R, D> {
+    /// All `knots` start out on top of each other at the origin
+    fn with_rule(knots: NonZeroUsize, rule: R) -> Self {
+        let knots = knots.get();
+
+        let mut start = HashSet::with_capacity(1024);
+        start.insert(Coord::default());
 
-        match c {
-            'u' | 'U' => Up,
-            'r' | 'R' => Right,
-            'd' | 'D' => Down,
-            'l' | 'L' => Left,
-            _ => panic!("Unknown direction: {c}"),
+        Rope {
+            knots: vec![Coord::default(); knots],
+            visited: vec![start; knots],
+            rule,
         }
     }
-}
 
-struct Move {
-    dir: Dir,
-    count: usize,
-}
+    /// Runs `moves` one step at a time, yielding where all the knots are after each
+    fn steps<'a>(&'a mut self, moves: &'a [Move<D>]) -> impl Iterator<Item = Vec<Coord<D>>> + 'a {
+        moves
+            .iter()
+            .flat_map(|m| std::iter::repeat_n(m.dir, m.count))
+            .map(move |dir| {
+                self.step(dir);
+                self.knots.clone()
+            })
+    }
 
-impl From<&str> for Move {
-    fn from(s: &str) -> Self {
-        Move {
-            dir: s.chars().next().unwrap().try_into().unwrap(),
-            count: s[2..].parse().unwrap(),
+    fn do_move(&mut self, m: Move<D>) {
+        for _ in 0..(m.count) {
+            self.step(m.dir);
         }
     }
-}
 
-impl Knot {
-    fn do_moves(&mut self, entry: &str, tail_pos: &mut HashSet<Coord>) {
-        let m = Move::from(entry);
+    /// Moves the head one step and lets the rest of the rope catch up
+    fn step(&mut self, dir: Dir<D>) {
+        self.knots[0].move_head(dir);
+        for i in 1..self.knots.len() {
+            self.knots[i] = self.rule.follow(self.knots[i], self.knots[i - 1]);
+        }
 
-        for _ in 0..(m.count) {
-            self.head.move_head(m.dir);
-            self.tails[0].adjust_tail(self.head);
-            for i in 1..TAIL_N {
-                self.tails[i].adjust_tail(self.tails[i - 1]);
-            }
-            tail_pos.insert(self.tails[TAIL_N - 1]);
+        for (visited, &knot) in self.visited.iter_mut().zip(&self.knots) {
+            visited.insert(knot);
         }
     }
+
+    /// How many different places knot `idx` has been, where 0 is the head
+    fn visited(&self, idx: usize) -> usize {
+        self.visited[idx].len()
+    }
+
+    fn tail_visited(&self) -> usize {
+        self.visited(self.knots.len() - 1)
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    pub(crate) fn knots(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    fn test_data() -> &'static str {
+        "R 4
+U 4
+L 3
+D 1
+R 4
+D 1
+L 5
+R 2"
+    }
+
+    fn larger_data() -> &'static str {
+        "R 5
+U 8
+L 8
+D 3
+R 17
+D 10
+L 25
+U 20"
+    }
+
+    #[test]
+    fn example() {
+        let rope = simulate(test_data(), knots(2)).unwrap();
+        assert_eq!(13, rope.tail_visited());
+        assert_eq!(rope.visited(1), rope.tail_visited());
+
+        let rope = simulate(test_data(), knots(10)).unwrap();
+        assert_eq!(1, rope.tail_visited());
+        assert_eq!(13, rope.visited(1));
+    }
+
+    #[test]
+    fn larger_example() {
+        assert_eq!(
+            36,
+            simulate(larger_data(), knots(10)).unwrap().tail_visited()
+        );
+    }
+
+    #[test]
+    fn diagonals() {
+        let rope = simulate("UR 3", knots(2)).unwrap();
+        assert_eq!(vec![Coord([3, 3]), Coord([2, 2])], rope.knots);
+        assert_eq!(3, rope.tail_visited());
+
+        // Wagging the head back and forth the tail never has to move
+        let rope = simulate("UR 1\nDL 1\nDR 1\nUL 1", knots(2)).unwrap();
+        assert_eq!(Coord::default(), rope.knots[0]);
+        assert_eq!(1, rope.tail_visited());
+    }
+
+    #[test]
+    fn stepping() {
+        let moves = parse_moves(test_data()).unwrap();
+        let mut rope = Rope::new(knots(2));
+        let steps: Vec<Vec<Coord>> = rope.steps(&moves).collect();
+
+        assert_eq!(24, steps.len());
+        assert_eq!(vec![Coord([1, 0]), Coord::default()], steps[0]);
+        assert_eq!(vec![Coord([2, 0]), Coord([1, 0])], steps[1]);
+        assert_eq!(rope.knots, steps[23]);
+
+        let tails: HashSet<Coord> = steps.iter().map(|knots| knots[1]).collect();
+        assert_eq!(rope.tail_visited(), tails.len());
+    }
+
     #[test]
-    fn example() {}
+    fn three_d() {
+        // Forwards and backwards are along z, and any two or three directions go diagonally
+        let rope: Rope<Standard, 3> = simulate_with("F 3\nURB 2\nL 1", knots(2), Standard).unwrap();
+        assert_eq!(vec![Coord([1, 2, 1]), Coord([1, 1, 1])], rope.knots);
+        assert_eq!(4, rope.tail_visited());
+
+        // Moves in a plane give the same answers whatever the dimension
+        let flat: Rope<Standard, 3> = simulate_with(larger_data(), knots(10), Standard).unwrap();
+        assert_eq!(36, flat.tail_visited());
+        assert!(flat.knots.iter().all(|knot| knot.0[2] == 0));
+
+        // A 1D rope has no y axis, so it can only go left and right
+        assert!(simulate_with::<_, 1>("U 1", knots(2), Standard).is_err());
+        assert_eq!(
+            4,
+            simulate_with::<_, 1>("R 3", knots(1), Standard)
+                .unwrap()
+                .tail_visited()
+        );
+    }
+
+    #[test]
+    fn rope_lengths() {
+        // A lone head just goes where it's told
+        let head = simulate(test_data(), knots(1)).unwrap();
+        assert_eq!(head.visited(0), head.tail_visited());
+        assert_eq!(Coord([2, 2]), head.knots[0]);
+
+        // Every knot along a longer rope visits no more places than the one ahead of it
+        let rope = simulate(larger_data(), knots(10)).unwrap();
+        for idx in 1..10 {
+            assert!(rope.visited(idx) <= rope.visited(idx - 1));
+        }
+    }
 }
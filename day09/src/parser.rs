@@ -0,0 +1,189 @@
+use std::fmt::{Display, Formatter};
+
+use crate::Dir;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Move<const D: usize = 2> {
+    pub dir: Dir<D>,
+    pub count: usize,
+}
+
+/// Why a line isn't a move
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum ErrorKind {
+    /// A count with no direction in front of it
+    MissingDir,
+    UnknownDir(String),
+    MissingCount,
+    BadCount(String),
+    /// Anything after the count other than a comment
+    Trailing(String),
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::MissingDir => write!(f, "no direction"),
+            ErrorKind::UnknownDir(dir) => write!(f, "unknown direction `{dir}`"),
+            ErrorKind::MissingCount => write!(f, "no count"),
+            ErrorKind::BadCount(count) => write!(f, "`{count}` isn't a count"),
+            ErrorKind::Trailing(rest) => write!(f, "unexpected `{rest}` after the count"),
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct ParseError {
+    pub line_no: usize,
+    pub kind: ErrorKind,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line_no, self.kind)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Reads one move per line, like `R 4` or `ul 3`.  Directions are `R` and `L` along x, `U` and
+/// `D` along y and `F` and `B` along z, in either case, with a few together for a diagonal.  The
+/// count can follow with any amount of space or none at all.  Blank lines and anything after a
+/// `#` are ignored
+pub fn parse_moves<const D: usize>(data: &str) -> Result<Vec<Move<D>>, ParseError> {
+    data.lines()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let line = line.split_once('#').map_or(line, |(line, _)| line).trim();
+            (!line.is_empty()).then(|| {
+                parse_move(line).map_err(|kind| ParseError {
+                    line_no: idx + 1,
+                    kind,
+                })
+            })
+        })
+        .collect()
+}
+
+fn parse_move<const D: usize>(line: &str) -> Result<Move<D>, ErrorKind> {
+    let split = line
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(line.len());
+    let (dir, rest) = line.split_at(split);
+
+    if dir.is_empty() {
+        return Err(ErrorKind::MissingDir);
+    }
+    let dir = parse_dir(dir).ok_or_else(|| ErrorKind::UnknownDir(dir.to_string()))?;
+
+    let mut rest = rest.split_whitespace();
+    let count = rest.next().ok_or(ErrorKind::MissingCount)?;
+    let count = count
+        .parse()
+        .map_err(|_| ErrorKind::BadCount(count.to_string()))?;
+
+    match rest.next() {
+        Some(extra) => Err(ErrorKind::Trailing(extra.to_string())),
+        None => Ok(Move { dir, count }),
+    }
+}
+
+/// Each letter moves along its own axis, which has to be one of the first `D`, and none can be
+/// used twice
+fn parse_dir<const D: usize>(dir: &str) -> Option<Dir<D>> {
+    let mut step = [0; D];
+
+    for c in dir.chars() {
+        let (axis, by) = match c.to_ascii_uppercase() {
+            'R' => (0, 1),
+            'L' => (0, -1),
+            'U' => (1, 1),
+            'D' => (1, -1),
+            'F' => (2, 1),
+            'B' => (2, -1),
+            _ => return None,
+        };
+
+        match step.get_mut(axis) {
+            Some(s) if *s == 0 => *s = by,
+            _ => return None,
+        }
+    }
+
+    Some(Dir(step))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants() {
+        let moves = parse_moves("R 4\n  u\t12  \n\nl3 # left\n# nothing\nDR 2\nlu   1").unwrap();
+        assert_eq!(
+            vec![
+                Move {
+                    dir: Dir([1, 0]),
+                    count: 4
+                },
+                Move {
+                    dir: Dir([0, 1]),
+                    count: 12
+                },
+                Move {
+                    dir: Dir([-1, 0]),
+                    count: 3
+                },
+                Move {
+                    dir: Dir([1, -1]),
+                    count: 2
+                },
+                Move {
+                    dir: Dir([-1, 1]),
+                    count: 1
+                },
+            ],
+            moves
+        );
+        assert_eq!(Ok(Vec::new()), parse_moves::<2>("\n  # only comments\n"));
+
+        // More dimensions bring in forwards and backwards
+        let moves = parse_moves("lfu 2\nB1").unwrap();
+        assert_eq!(
+            vec![
+                Move {
+                    dir: Dir([-1, 1, 1]),
+                    count: 2
+                },
+                Move {
+                    dir: Dir([0, 0, -1]),
+                    count: 1
+                },
+            ],
+            moves
+        );
+        assert!(parse_moves::<3>("FB 1").is_err());
+    }
+
+    #[test]
+    fn bad_moves() {
+        for (data, line_no, kind) in [
+            ("U", 1, ErrorKind::MissingCount),
+            ("R 1\nU # 4", 2, ErrorKind::MissingCount),
+            ("R 1\n\n4", 3, ErrorKind::MissingDir),
+            ("X 4", 1, ErrorKind::UnknownDir("X".to_string())),
+            ("UD 4", 1, ErrorKind::UnknownDir("UD".to_string())),
+            ("RR 4", 1, ErrorKind::UnknownDir("RR".to_string())),
+            ("F 4", 1, ErrorKind::UnknownDir("F".to_string())),
+            ("U -4", 1, ErrorKind::BadCount("-4".to_string())),
+            ("U 4x", 1, ErrorKind::BadCount("4x".to_string())),
+            ("U 4 5", 1, ErrorKind::Trailing("5".to_string())),
+        ] {
+            assert_eq!(
+                Err(ParseError { line_no, kind }),
+                parse_moves::<2>(data),
+                "{data}"
+            );
+        }
+    }
+}
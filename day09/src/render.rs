@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+
+use crate::Coord;
+
+/// Draws the rope like the puzzle does, with `H` for the head, the next nine numbered from 1
+/// and any after that as `*`.  Knots nearer the head cover the ones behind them, and `s` marks the start if nothing else
+/// is there.  The grid only goes as far as it needs to, with up being up.  Only 2D ropes can be
+/// drawn
+pub fn draw(knots: &[Coord]) -> String {
+    let start = Coord::default();
+
+    grid(knots.iter().copied().chain([start]), |at| {
+        match knots.iter().position(|&knot| knot == at) {
+            Some(0) => 'H',
+            Some(idx @ 1..=9) => std::char::from_digit(idx as u32, 10).unwrap(),
+            Some(_) => '*',
+            None if at == start => 's',
+            None => '.',
+        }
+    })
+}
+
+/// Everywhere a knot has been as `#`, apart from the start which is `s`
+pub fn draw_visited(visited: &HashSet<Coord>) -> String {
+    let start = Coord::default();
+
+    grid(visited.iter().copied().chain([start]), |at| {
+        if at == start {
+            's'
+        } else if visited.contains(&at) {
+            '#'
+        } else {
+            '.'
+        }
+    })
+}
+
+/// Just big enough to fit everything in `cells`, with the top row first
+fn grid(cells: impl Iterator<Item = Coord> + Clone, cell: impl Fn(Coord) -> char) -> String {
+    let (x_min, x_max) = bounds(cells.clone().map(|Coord([x, _])| x));
+    let (y_min, y_max) = bounds(cells.map(|Coord([_, y])| y));
+    let mut out = String::with_capacity(capacity((x_min, x_max), (y_min, y_max)));
+
+    for y in (y_min..=y_max).rev() {
+        out.extend((x_min..=x_max).map(|x| cell(Coord([x, y]))));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// How many characters a grid covering both ranges takes, counting the newlines, or nothing if
+/// that's more than could ever fit
+fn capacity((x_min, x_max): (i32, i32), (y_min, y_max): (i32, i32)) -> usize {
+    let width = x_max.abs_diff(x_min) as usize + 2;
+    let height = y_max.abs_diff(y_min) as usize + 1;
+    width.checked_mul(height).unwrap_or(0)
+}
+
+fn bounds(values: impl Iterator<Item = i32>) -> (i32, i32) {
+    values.fold((i32::MAX, i32::MIN), |(lo, hi), v| (lo.min(v), hi.max(v)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulate;
+    use crate::tests::knots;
+
+    #[test]
+    fn drawing() {
+        let rope = simulate("R 4\nU 4", knots(10)).unwrap();
+        assert_eq!("....H\n....1\n..432\n.5...\n6....\n", draw(&rope.knots));
+
+        // A head on its own covers the start
+        assert_eq!("H\n", draw(&[Coord::default()]));
+
+        // Knots past the ninth are all stars
+        let knots: Vec<Coord> = (0..30).map(|x| Coord([x, 0])).collect();
+        assert_eq!(format!("H123456789{}\n", "*".repeat(20)), draw(&knots));
+
+        assert_eq!(5, capacity((-1, 2), (5, 5)));
+        assert_eq!(0, capacity((i32::MIN, i32::MAX), (i32::MIN, i32::MAX)));
+    }
+
+    #[test]
+    fn visited_map() {
+        let data = "R 5\nU 8\nL 8\nD 3\nR 17\nD 10\nL 25\nU 20";
+        let rope = simulate(data, knots(10)).unwrap();
+
+        assert_eq!(
+            "H...........\n\
+             1...........\n\
+             2...........\n\
+             3...........\n\
+             4...........\n\
+             5...........\n\
+             6...........\n\
+             7...........\n\
+             8...........\n\
+             9...........\n\
+             ............\n\
+             ............\n\
+             ............\n\
+             ............\n\
+             ............\n\
+             ...........s\n",
+            draw(&rope.knots)
+        );
+
+        assert_eq!(
+            "#.....................\n\
+             #.............###.....\n\
+             #............#...#....\n\
+             .#..........#.....#...\n\
+             ..#..........#.....#..\n\
+             ...#........#.......#.\n\
+             ....#......s.........#\n\
+             .....#..............#.\n\
+             ......#............#..\n\
+             .......#..........#...\n\
+             ........#........#....\n\
+             .........########.....\n",
+            draw_visited(&rope.visited[9])
+        );
+    }
+}
@@ -1,12 +1,17 @@
+mod ocr;
+
+use crate::ocr::OcrError;
+
 const PART_1: bool = false;
 
 static PART_1_DATA: &str = include_str!("input");
 
-fn main() {
+fn main() -> Result<(), OcrError> {
     if PART_1 {
         part1();
+        Ok(())
     } else {
-        part2();
+        part2()
     }
 }
 
@@ -16,10 +21,12 @@ fn part1() {
     println!("{}", comp.signal_total());
 }
 
-fn part2() {
+fn part2() -> Result<(), OcrError> {
     let mut comp = Comp::default();
     comp.run_all(PART_1_DATA);
     println!("{}", comp.output_sprites);
+    println!("{}", ocr::read(&comp.output_sprites)?);
+    Ok(())
 }
 
 const COLS: usize = 40;
@@ -132,7 +139,14 @@ mod tests {
         assert_eq!(vec![420, 1140, 1800, 2940, 2880, 3960], comp.found_signals);
         assert_eq!(13140, comp.signal_total());
 
-        assert_eq!(example_output().to_string(), comp.output_sprites)
+        assert_eq!(example_output().to_string(), comp.output_sprites);
+
+        // The example draws stripes rather than letters
+        let Err(OcrError::Unknown { text, glyphs }) = ocr::read(&comp.output_sprites) else {
+            panic!("Read letters in the stripes");
+        };
+        assert_eq!("????????", text);
+        assert_eq!(8, glyphs.len());
     }
 
     fn test_data() -> &'static str {
@@ -0,0 +1,213 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{COLS, ROWS};
+
+/// How many columns each letter takes up on the screen, including the blank one after it
+const CELL: usize = 5;
+const GLYPH: usize = 4;
+
+/// The letters that turn up in the puzzles, one row after another, 4 pixels wide and 6 tall
+const FONT: [(char, &str); 17] = [
+    ('A', ".##.#..##..######..##..#"),
+    ('B', "###.#..####.#..##..####."),
+    ('C', ".##.#..##...#...#..#.##."),
+    ('E', "#####...###.#...#...####"),
+    ('F', "#####...###.#...#...#..."),
+    ('G', ".##.#..##...#.###..#.###"),
+    ('H', "#..##..######..##..##..#"),
+    ('I', ".###..#...#...#...#..###"),
+    ('J', "..##...#...#...##..#.##."),
+    ('K', "#..##.#.##..#.#.#.#.#..#"),
+    ('L', "#...#...#...#...#...####"),
+    ('O', ".##.#..##..##..##..#.##."),
+    ('P', "###.#..##..####.#...#..."),
+    ('R', "###.#..##..####.#.#.#..#"),
+    ('S', ".####...#....##....####."),
+    ('U', "#..##..##..##..##..#.##."),
+    ('Z', "####...#..#..#..#...####"),
+];
+
+/// A letter that couldn't be read, `pos` cells from the left
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Glyph {
+    pub pos: usize,
+    /// The whole cell, including the gap after it in case something is lit there
+    pub rows: Vec<String>,
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum OcrError {
+    /// Not a screen of `#` and `.` the size of the CRT
+    BadScreen(String),
+    /// The letters that could be read, with `?` in place of the rest
+    Unknown { text: String, glyphs: Vec<Glyph> },
+}
+
+impl Display for OcrError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OcrError::BadScreen(why) => write!(f, "can't read the screen: {why}"),
+            OcrError::Unknown { text, glyphs } => {
+                write!(f, "read `{text}` but didn't know")?;
+                for glyph in glyphs {
+                    write!(f, "\nletter {}:", glyph.pos + 1)?;
+                    for row in &glyph.rows {
+                        write!(f, "\n  {row}")?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Reads the letters off a screen drawn like [`crate::Comp::output_sprites`].  Blank cells come
+/// out as spaces
+pub fn read(screen: &str) -> Result<String, OcrError> {
+    let rows: Vec<&str> = screen.lines().collect();
+    if rows.len() != ROWS {
+        return Err(OcrError::BadScreen(format!(
+            "{} rows instead of {ROWS}",
+            rows.len()
+        )));
+    }
+
+    let mut pixels = Vec::with_capacity(ROWS);
+    for (idx, row) in rows.iter().enumerate() {
+        let row: Vec<bool> = row
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                _ => Err(OcrError::BadScreen(format!("`{c}` in row {}", idx + 1))),
+            })
+            .collect::<Result<_, _>>()?;
+
+        if row.len() != COLS {
+            return Err(OcrError::BadScreen(format!(
+                "row {} is {} wide instead of {COLS}",
+                idx + 1,
+                row.len()
+            )));
+        }
+        pixels.push(row);
+    }
+
+    let mut text = String::with_capacity(COLS / CELL);
+    let mut glyphs = Vec::new();
+
+    for pos in 0..COLS / CELL {
+        let cell: Vec<&[bool]> = pixels
+            .iter()
+            .map(|row| &row[pos * CELL..(pos + 1) * CELL])
+            .collect();
+
+        match letter(&cell) {
+            Some(c) => text.push(c),
+            None => {
+                text.push('?');
+                glyphs.push(Glyph {
+                    pos,
+                    rows: cell.iter().map(|row| draw(row)).collect(),
+                });
+            }
+        }
+    }
+
+    if glyphs.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::Unknown { text, glyphs })
+    }
+}
+
+fn letter(cell: &[&[bool]]) -> Option<char> {
+    if cell.iter().any(|row| row[GLYPH..].contains(&true)) {
+        return None;
+    }
+
+    let key: String = cell.iter().map(|row| draw(&row[..GLYPH])).collect();
+    if !key.contains('#') {
+        return Some(' ');
+    }
+
+    FONT.iter()
+        .find(|(_, glyph)| *glyph == key)
+        .map(|&(c, _)| c)
+}
+
+fn draw(pixels: &[bool]) -> String {
+    pixels
+        .iter()
+        .map(|&on| if on { '#' } else { '.' })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters() {
+        let screen = "\
+###...##..###..#..#.####.#..#.####...##.
+#..#.#..#.#..#.#.#..#....#.#..#.......#.
+#..#.#..#.#..#.##...###..##...###.....#.
+###..####.###..#.#..#....#.#..#.......#.
+#....#..#.#....#.#..#....#.#..#....#..#.
+#....#..#.#....#..#.#....#..#.####..##..\n";
+        assert_eq!(Ok("PAPKFKEJ".to_string()), read(screen));
+
+        // Only the first and last letters are lit
+        let screen = "\
+.##..................................##.
+#..#..................................#.
+#.....................................#.
+#.....................................#.
+#..#...............................#..#.
+.##.................................##..";
+        assert_eq!(Ok("C      J".to_string()), read(screen));
+    }
+
+    #[test]
+    fn unknown() {
+        let screen = format!(
+            "#.#.{}\n{}\n.#..{}\n{}\n{}\n....#{}",
+            ".".repeat(36),
+            ".".repeat(40),
+            ".".repeat(36),
+            ".".repeat(40),
+            ".".repeat(40),
+            ".".repeat(35),
+        );
+
+        let Err(OcrError::Unknown { text, glyphs }) = read(&screen) else {
+            panic!("Read a screen with no letters on it");
+        };
+        assert_eq!("?       ", text);
+        assert_eq!(1, glyphs.len());
+        assert_eq!(
+            vec!["#.#..", ".....", ".#...", ".....", ".....", "....#"],
+            glyphs[0].rows
+        );
+    }
+
+    #[test]
+    fn bad_screens() {
+        assert!(matches!(read(""), Err(OcrError::BadScreen(_))));
+        assert!(matches!(
+            read(&format!("{}\n", ".".repeat(40)).repeat(5)),
+            Err(OcrError::BadScreen(_))
+        ));
+        assert!(matches!(
+            read(&format!("{}\n", ".".repeat(39)).repeat(6)),
+            Err(OcrError::BadScreen(_))
+        ));
+        assert!(matches!(
+            read(&format!("{}x\n", ".".repeat(39)).repeat(6)),
+            Err(OcrError::BadScreen(_))
+        ));
+    }
+}